@@ -0,0 +1,494 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive,
+    config::{self, Configuration},
+    download,
+    error::{LauncherError, Result},
+    integrity,
+    task::{self, Reporter},
+    toc::{self, Toc},
+    validate,
+};
+
+/// Where disabled addons are moved to, the client only loads what's in `Interface/AddOns`.
+const DISABLED: &str = "AddOns.disabled";
+
+/// An addon the server offers, see `Configuration::addons`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CatalogEntry {
+    /// Name of the addon's main folder.
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Zip with the addon folders at the top level.
+    pub url: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Every folder the archive installs, just `name` if empty.
+    #[serde(default)]
+    pub folders: Vec<String>,
+}
+
+impl CatalogEntry {
+    pub fn folders(&self) -> Vec<String> {
+        if self.folders.is_empty() {
+            vec![self.name.clone()]
+        } else {
+            self.folders.clone()
+        }
+    }
+
+    /// Names come from the server, make sure none of them points outside the AddOns folder.
+    fn check_names(&self) -> Result<()> {
+        check_folder(&self.name)?;
+        for folder in self.folders() {
+            check_folder(&folder)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the catalog has a different version than the one in `installed`.
+    pub fn update_available(&self, installed: &[InstalledAddon]) -> bool {
+        installed
+            .iter()
+            .find(|addon| addon.folder.eq_ignore_ascii_case(&self.name))
+            .map_or(false, |addon| {
+                addon.toc.version() != Some(self.version.as_str())
+            })
+    }
+}
+
+/// Something about an installed addon that keeps the client from loading it properly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// Made for another client, the value is the interface version from the .toc.
+    WrongInterface(Option<String>),
+    MissingDependency(String),
+    /// The real addon sits one or two folders further down, e.g. `Foo-master/Foo/Foo.toc`.
+    Nested(Vec<PathBuf>),
+    /// The folder holds a .toc with another name, the client wants `<folder>/<folder>.toc`.
+    Misnamed(String),
+    NoToc,
+}
+
+impl Problem {
+    /// Whether `fix_folders` can take care of it.
+    pub fn fixable(&self) -> bool {
+        matches!(self, Problem::Nested(_) | Problem::Misnamed(_))
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::WrongInterface(Some(interface)) => {
+                write!(
+                    f,
+                    "Made for interface {}, not {}",
+                    interface,
+                    toc::INTERFACE
+                )
+            }
+            Problem::WrongInterface(None) => write!(f, "No interface version"),
+            Problem::MissingDependency(dependency) => write!(f, "Needs {}", dependency),
+            Problem::Nested(folders) => {
+                let names: Vec<_> = folders
+                    .iter()
+                    .filter_map(|folder| folder.file_name())
+                    .map(|name| name.to_string_lossy())
+                    .collect();
+                write!(f, "Nested too deep, should be {}", names.join(", "))
+            }
+            Problem::Misnamed(name) => write!(f, "Folder should be named {}", name),
+            Problem::NoToc => write!(f, "Not an addon, no .toc found"),
+        }
+    }
+}
+
+/// An addon folder found on disk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstalledAddon {
+    pub folder: String,
+    pub toc: Toc,
+    pub enabled: bool,
+    pub problems: Vec<Problem>,
+}
+
+impl InstalledAddon {
+    pub fn title(&self) -> String {
+        self.toc.title().unwrap_or_else(|| self.folder.clone())
+    }
+}
+
+pub struct AddonManager {
+    cfg: Configuration,
+}
+
+impl AddonManager {
+    pub fn new(cfg: Configuration) -> Self {
+        Self { cfg }
+    }
+
+    /// Installs or updates `entry` in the background.
+    pub fn install(&self, entry: CatalogEntry, reporter: Reporter) {
+        let root = self.cfg.path.clone();
+        task::spawn(reporter, |reporter| async move {
+            install(&root, &entry, &reporter).await
+        });
+    }
+}
+
+pub fn interface_dir(root: &Path) -> PathBuf {
+    existing_dir(root, "Interface")
+}
+
+pub fn addons_dir(root: &Path) -> PathBuf {
+    existing_dir(&interface_dir(root), "AddOns")
+}
+
+fn disabled_dir(root: &Path) -> PathBuf {
+    interface_dir(root).join(DISABLED)
+}
+
+/// `dir/name` in whatever case it exists already, so we don't end up with `Interface` and `interface`.
+fn existing_dir(dir: &Path, name: &str) -> PathBuf {
+    validate::find(dir, name).unwrap_or_else(|| dir.join(name))
+}
+
+pub async fn fetch_catalog(url: &str) -> Result<Vec<CatalogEntry>> {
+    let body = download::client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    serde_json::from_slice(&body)
+        .map_err(|e| LauncherError::Config(format!("malformed addon catalog: {}", e)))
+}
+
+/// Every addon folder, enabled or not, with whatever is wrong with it. Blizzard's own addons are left out.
+pub fn installed(root: &Path) -> Vec<InstalledAddon> {
+    let mut addons = Vec::new();
+
+    for (dir, enabled) in [(addons_dir(root), true), (disabled_dir(root), false)] {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let folder = entry.file_name().to_string_lossy().to_string();
+            if folder.starts_with("Blizzard_") || !entry.path().is_dir() {
+                continue;
+            }
+
+            let (toc, problems) = match toc::find(&entry.path()) {
+                Some(path) => (Toc::read(&path).unwrap_or_default(), Vec::new()),
+                None => misplaced(&entry.path()),
+            };
+
+            addons.push(InstalledAddon {
+                folder,
+                toc,
+                enabled,
+                problems,
+            });
+        }
+    }
+
+    // dependencies can only be checked once everything is known
+    let loaded: Vec<String> = addons
+        .iter()
+        .filter(|addon| addon.enabled && addon.problems.is_empty())
+        .map(|addon| addon.folder.to_lowercase())
+        .collect();
+
+    for addon in addons.iter_mut().filter(|addon| addon.problems.is_empty()) {
+        if addon.toc.interface() != Some(toc::INTERFACE) {
+            let interface = addon.toc.interface().map(str::to_owned);
+            addon.problems.push(Problem::WrongInterface(interface));
+        }
+
+        for dependency in addon.toc.dependencies() {
+            if !dependency.starts_with("Blizzard_") && !loaded.contains(&dependency.to_lowercase())
+            {
+                addon.problems.push(Problem::MissingDependency(dependency));
+            }
+        }
+    }
+
+    addons.sort_by_key(|addon| addon.title().to_lowercase());
+    addons
+}
+
+/// Works out why a folder has no matching .toc, the toc that was found instead is returned along.
+fn misplaced(dir: &Path) -> (Toc, Vec<Problem>) {
+    let tocs = toc_files(dir);
+    if let Some(path) = tocs.first() {
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let toc = Toc::read(path).unwrap_or_default();
+        return (toc, vec![Problem::Misnamed(name)]);
+    }
+
+    // zips from github and the like wrap the addon in another folder, sometimes twice
+    let mut nested = Vec::new();
+    for child in subdirs(dir) {
+        if toc::find(&child).is_some() {
+            nested.push(child);
+        } else {
+            nested.extend(
+                subdirs(&child)
+                    .into_iter()
+                    .filter(|c| toc::find(c).is_some()),
+            );
+        }
+    }
+
+    if nested.is_empty() {
+        return (Toc::default(), vec![Problem::NoToc]);
+    }
+
+    let toc = toc::find(&nested[0])
+        .and_then(|path| Toc::read(&path).ok())
+        .unwrap_or_default();
+    (toc, vec![Problem::Nested(nested)])
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn toc_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |ext| ext.eq_ignore_ascii_case("toc"))
+        })
+        .collect()
+}
+
+/// Moves nested addons up into the AddOns folder and renames misnamed ones, see `Problem::fixable`.
+pub fn fix_folders(root: &Path, addon: &InstalledAddon) -> Result<()> {
+    let base = if addon.enabled {
+        addons_dir(root)
+    } else {
+        disabled_dir(root)
+    };
+    let dir = base.join(&addon.folder);
+
+    for problem in &addon.problems {
+        match problem {
+            Problem::Misnamed(name) => {
+                let target = base.join(name);
+                if target.exists() {
+                    return Err(LauncherError::Config(format!(
+                        "can't rename {}, {} exists already",
+                        addon.folder, name
+                    )));
+                }
+                fs::rename(&dir, target)?;
+            }
+            Problem::Nested(folders) => {
+                // never replace another addon, only the outer folder itself may be in the way
+                for folder in folders {
+                    let name = folder.file_name().unwrap_or_default();
+                    let outer = name.to_string_lossy().eq_ignore_ascii_case(&addon.folder);
+                    if !outer && base.join(name).exists() {
+                        return Err(LauncherError::Config(format!(
+                            "can't move {} out of {}, it exists already",
+                            name.to_string_lossy(),
+                            addon.folder
+                        )));
+                    }
+                }
+
+                // the outer folder might have the same name as the one inside, get it out of the way first
+                let outer = base.join(format!(".{}.unnest", addon.folder));
+                fs::rename(&dir, &outer)?;
+
+                for folder in folders {
+                    let inner = outer.join(folder.strip_prefix(&dir).unwrap_or(folder));
+                    let target = base.join(folder.file_name().unwrap_or_default());
+                    fs::rename(inner, target)?;
+                }
+
+                // whatever is left is a readme or license, nothing the client loads
+                fs::remove_dir_all(outer)?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads `entry`, checks it and swaps its folders in, replacing an older version.
+pub async fn install(root: &Path, entry: &CatalogEntry, reporter: &Reporter) -> Result<()> {
+    entry.check_names()?;
+
+    reporter.phase(format!("Downloading {}..", entry.name));
+
+    let interface = interface_dir(root);
+    fs::create_dir_all(&interface)?;
+
+    let zip = interface.join(format!("{}.zip", entry.name));
+    download::download(&entry.url, &zip, &reporter.cancel_token(), |progress| {
+        reporter.download(&entry.name, progress)
+    })
+    .await?;
+
+    let staging = interface.join(format!(".{}.staging", entry.name));
+    let installed = tokio::task::block_in_place(|| -> Result<()> {
+        if let Some(sha256) = &entry.sha256 {
+            let (hash, _) = integrity::hash_file(&zip)?;
+            if !hash.eq_ignore_ascii_case(sha256) {
+                return Err(LauncherError::Integrity(format!(
+                    "{} does not match the catalog",
+                    entry.name
+                )));
+            }
+        }
+
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        archive::extract(&zip, &staging, reporter)?;
+
+        reporter.phase(format!("Installing {}..", entry.name));
+        swap_in(root, entry, &staging)
+    });
+
+    let _ = fs::remove_file(&zip);
+    let _ = fs::remove_dir_all(&staging);
+
+    installed
+}
+
+/// Moves the extracted folders into `Interface/AddOns`, an archive without folders is taken as the addon itself.
+fn swap_in(root: &Path, entry: &CatalogEntry, staging: &Path) -> Result<()> {
+    entry.check_names()?;
+
+    let addons = addons_dir(root);
+    fs::create_dir_all(&addons)?;
+
+    let entries: Vec<PathBuf> = fs::read_dir(staging)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+
+    let flat = entries.iter().any(|path| {
+        path.extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("toc"))
+    });
+
+    let folders: Vec<(PathBuf, OsString)> = if flat {
+        vec![(staging.to_path_buf(), OsString::from(&entry.name))]
+    } else {
+        entries
+            .into_iter()
+            .filter(|path| path.is_dir())
+            .filter_map(|path| Some((path.clone(), path.file_name()?.to_owned())))
+            .collect()
+    };
+
+    if folders.is_empty() {
+        return Err(LauncherError::Config(format!(
+            "the archive of {} has no addon in it",
+            entry.name
+        )));
+    }
+
+    // the old version goes away completely, leftover files of it would still be loaded
+    uninstall(root, &entry.folders())?;
+
+    for (folder, name) in folders {
+        let target = addons.join(&name);
+
+        remove(&target)?;
+        remove(&disabled_dir(root).join(&name))?;
+        fs::rename(&folder, &target)?;
+    }
+
+    Ok(())
+}
+
+/// `folder` has to be a plain folder name, no separators, `..` or drive letters.
+fn check_folder(folder: &str) -> Result<()> {
+    if config::is_plain_name(folder) {
+        return Ok(());
+    }
+
+    Err(LauncherError::Config(format!(
+        "{:?} is not a valid addon folder name",
+        folder
+    )))
+}
+
+fn remove(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the given addon folders, enabled or not.
+pub fn uninstall(root: &Path, folders: &[String]) -> Result<()> {
+    for folder in folders {
+        check_folder(folder)?;
+    }
+
+    for folder in folders {
+        remove(&addons_dir(root).join(folder))?;
+        remove(&disabled_dir(root).join(folder))?;
+    }
+
+    Ok(())
+}
+
+/// Moves an addon between `AddOns` and `AddOns.disabled`, that way it works for every account and character.
+pub fn set_enabled(root: &Path, folder: &str, enabled: bool) -> Result<()> {
+    let (from, to) = if enabled {
+        (disabled_dir(root), addons_dir(root))
+    } else {
+        (addons_dir(root), disabled_dir(root))
+    };
+
+    check_folder(folder)?;
+
+    let source = from.join(folder);
+    if !source.exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&to)?;
+    let target = to.join(folder);
+    remove(&target)?;
+    fs::rename(source, target)?;
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use zip::{result::ZipError, ZipArchive};
+
+use crate::{error::Result, task::Reporter};
+
+/// Unpacks a zip archive into `dest`, refusing entries that would end up outside of it.
+///
+/// Progress is reported in uncompressed bytes over the whole archive, the entry
+/// currently being written shows up as the phase. Cancelling stops between chunks.
+pub fn extract(archive: &Path, dest: &Path, reporter: &Reporter) -> Result<()> {
+    let file = OpenOptions::new().read(true).open(archive)?;
+    let mut zip = ZipArchive::new(file)?;
+
+    let mut total = 0;
+    for i in 0..zip.len() {
+        total += zip.by_index_raw(i)?.size();
+    }
+
+    let mut extracted = 0;
+    let mut buf = vec![0; 256 * 1024];
+
+    for i in 0..zip.len() {
+        reporter.check_cancelled()?;
+
+        let mut entry = zip.by_index(i)?;
+
+        // enclosed_name drops absolute paths and anything with `..` in it
+        let dest_path = match entry.enclosed_name() {
+            Some(name) => dest.join(name),
+            None => {
+                eprintln!("Refusing to extract {}", entry.name());
+                return Err(
+                    ZipError::InvalidArchive("archive entry escapes the game folder").into(),
+                );
+            }
+        };
+
+        if entry.is_dir() {
+            fs::create_dir_all(&dest_path)?;
+            continue;
+        }
+
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        reporter.phase(format!("Extracting {}", entry.name()));
+
+        let mut dest_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&dest_path)?;
+
+        let written = (|| -> Result<()> {
+            loop {
+                reporter.check_cancelled()?;
+
+                let read = entry.read(&mut buf)?;
+                if read == 0 {
+                    return Ok(());
+                }
+
+                dest_file.write_all(&buf[..read])?;
+                extracted += read as u64;
+
+                reporter.progress("game files", extracted, Some(total));
+            }
+        })();
+
+        if let Err(e) = written {
+            // don't leave a truncated file behind
+            drop(dest_file);
+            let _ = fs::remove_file(&dest_path);
+            return Err(e);
+        }
+
+        if let Some(modified) = modified(&entry) {
+            dest_file.set_modified(modified)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Modification time stored in the archive, zip dates don't carry a timezone so they're taken as UTC.
+fn modified(entry: &zip::read::ZipFile) -> Option<SystemTime> {
+    entry.last_modified().to_time().ok().map(SystemTime::from)
+}
+
+/// Writes a single entry of the archive to `to`, used to fix files without extracting everything again.
+pub fn extract_file(archive: &Path, name: &str, to: &Path) -> Result<()> {
+    let file = OpenOptions::new().read(true).open(archive)?;
+    let mut zip = ZipArchive::new(file)?;
+
+    // archives made on windows might use backslashes
+    let name = if zip.file_names().any(|entry| entry == name) {
+        name.to_owned()
+    } else {
+        name.replace('/', "\\")
+    };
+
+    let mut entry = zip.by_name(&name)?;
+
+    let mut dest_file = fs::File::create(to)?;
+    std::io::copy(&mut entry, &mut dest_file)?;
+    dest_file.sync_all()?;
+
+    Ok(())
+}
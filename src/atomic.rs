@@ -0,0 +1,34 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where the previous version of `target` is kept while an update is in flight.
+pub fn backup_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    target.with_file_name(name)
+}
+
+/// Moves a fully written + verified file from the same directory over `target`, without a backup.
+///
+/// `staged` has to live on the same filesystem as `target` so the rename can't leave a half written file behind.
+pub fn replace(staged: &Path, target: &Path) -> io::Result<()> {
+    fs::File::open(staged)?.sync_all()?;
+    fs::rename(staged, target)?;
+    sync_dir(target)
+}
+
+/// Flushes the directory entry of `path`, windows doesn't let us open directories for that.
+#[cfg(unix)]
+pub fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::File::open(parent)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
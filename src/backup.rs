@@ -0,0 +1,292 @@
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    archive, atomic,
+    config::Configuration,
+    error::Result,
+    task::{self, Reporter},
+    validate,
+};
+
+/// Folders holding the player's settings, keybindings, macros and addons.
+const FOLDERS: [&str; 2] = ["WTF", "Interface"];
+
+const BACKUPS: &str = "Backups";
+
+/// Snapshots beyond this many are deleted, oldest first.
+const KEEP: usize = 10;
+
+/// A zip of `WTF/` and `Interface/` taken at some point, see `snapshot`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub created: SystemTime,
+    pub size: u64,
+}
+
+impl Snapshot {
+    /// When it was taken, in UTC since we don't pull in a timezone database for this.
+    pub fn name(&self) -> String {
+        let secs = self
+            .created
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let time = secs % 86400;
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )
+    }
+}
+
+/// Days since 1970-01-01 to a calendar date, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+pub struct BackupManager {
+    cfg: Configuration,
+}
+
+impl BackupManager {
+    pub fn new(cfg: Configuration) -> Self {
+        Self { cfg }
+    }
+
+    pub fn snapshot(&self, reporter: Reporter) {
+        let root = self.cfg.path.clone();
+        task::spawn(reporter, |reporter| async move {
+            tokio::task::block_in_place(|| snapshot(&root, &reporter)).map(|_| ())
+        });
+    }
+
+    pub fn restore(&self, snapshot: PathBuf, reporter: Reporter) {
+        let root = self.cfg.path.clone();
+        task::spawn(reporter, |reporter| async move {
+            tokio::task::block_in_place(|| restore(&root, &snapshot, &reporter))
+        });
+    }
+}
+
+pub fn backups_dir(root: &Path) -> PathBuf {
+    root.join(BACKUPS)
+}
+
+/// Every snapshot of the installation at `root`, newest first.
+pub fn snapshots(root: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(backups_dir(root)) else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "zip"))
+        .filter_map(|entry| {
+            let path = entry.path();
+            // the name is the creation time, mtimes get lost when the folder is copied around
+            let millis = path
+                .file_stem()?
+                .to_str()?
+                .strip_prefix("snapshot-")?
+                .parse()
+                .ok()?;
+
+            Some(Snapshot {
+                created: UNIX_EPOCH + Duration::from_millis(millis),
+                size: entry.metadata().ok()?.len(),
+                path,
+            })
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| b.created.cmp(&a.created));
+    snapshots
+}
+
+/// Zips `WTF/` and `Interface/` into a new snapshot, returns none if there was nothing to back up.
+pub fn snapshot(root: &Path, reporter: &Reporter) -> Result<Option<PathBuf>> {
+    let folders: Vec<PathBuf> = FOLDERS
+        .iter()
+        .filter_map(|folder| validate::find(root, folder))
+        .filter(|path| path.is_dir())
+        .collect();
+
+    if folders.is_empty() {
+        return Ok(None);
+    }
+
+    reporter.phase("Backing up settings and addons..");
+
+    let mut files = Vec::new();
+    for folder in &folders {
+        walk(folder, &mut files)?;
+    }
+    let total = files
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    let dir = backups_dir(root);
+    fs::create_dir_all(&dir)?;
+
+    // two snapshots in a row, e.g. a backup right before an install, must not replace each other
+    let mut millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    while dir.join(format!("snapshot-{}.zip", millis)).exists() {
+        millis += 1;
+    }
+    let target = dir.join(format!("snapshot-{}.zip", millis));
+    let staged = dir.join(format!("snapshot-{}.zip.part", millis));
+
+    if let Err(e) = write_zip(root, &files, &staged, total, reporter) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    atomic::replace(&staged, &target)?;
+
+    for old in snapshots(root).into_iter().skip(KEEP) {
+        let _ = fs::remove_file(old.path);
+    }
+
+    eprintln!("Backed up settings to {}", target.display());
+    Ok(Some(target))
+}
+
+/// Every file and directory under `dir`, directories first so empty ones survive a restore.
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    out.push(dir.to_path_buf());
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn write_zip(
+    root: &Path,
+    files: &[PathBuf],
+    to: &Path,
+    total: u64,
+    reporter: &Reporter,
+) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(to)?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut done = 0;
+
+    for path in files {
+        reporter.check_cancelled()?;
+
+        // zip names always use forward slashes
+        let name = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if path.is_dir() {
+            zip.add_directory(name, options)?;
+            continue;
+        }
+
+        zip.start_file(name, options)?;
+        done += io::copy(&mut fs::File::open(path)?, &mut zip)?;
+        reporter.progress("backup", done, Some(total));
+    }
+
+    zip.finish()?.sync_all()?;
+    Ok(())
+}
+
+/// Puts `WTF/` and `Interface/` back the way they were in `snapshot`, folders it doesn't contain are left alone.
+pub fn restore(root: &Path, snapshot: &Path, reporter: &Reporter) -> Result<()> {
+    reporter.phase("Restoring backup..");
+
+    let staging = root.join(".restore");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+
+    if let Err(e) = archive::extract(snapshot, &staging, reporter) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    for folder in FOLDERS {
+        let Some(restored) = validate::find(&staging, folder) else {
+            continue;
+        };
+
+        // swap the whole folder so nothing of the current setup is mixed into the restored one
+        let current = validate::find(root, folder).unwrap_or_else(|| root.join(folder));
+        let old = root.join(format!(".{}.old", folder));
+
+        if current.exists() {
+            if old.exists() {
+                fs::remove_dir_all(&old)?;
+            }
+            fs::rename(&current, &old)?;
+        }
+
+        if let Err(e) = fs::rename(&restored, &current) {
+            if old.exists() {
+                fs::rename(&old, &current)?;
+            }
+            return Err(e.into());
+        }
+
+        if old.exists() {
+            fs::remove_dir_all(&old)?;
+        }
+    }
+
+    fs::remove_dir_all(&staging)?;
+    Ok(())
+}
+
+pub fn delete(snapshot: &Snapshot) -> Result<()> {
+    fs::remove_file(&snapshot.path)?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, sync::mpsc};
+
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+
+use crate::{
+    config::Configuration,
+    error::{LauncherError, Result},
+    installer, launch, repair,
+    task::{self, Reporter, TaskEvent, TaskKind},
+    updater,
+    validate::{self, Verdict},
+};
+
+/// Runs one of these instead of opening the window, e.g. `launcher update`.
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum Command {
+    /// Install the client from the configured source and patch it.
+    Install,
+    /// Bring the patch files of the selected installation up to date.
+    Update,
+    /// Check every file of the base client without changing anything.
+    Verify,
+    /// Replace damaged client files, then update.
+    Repair,
+    /// Start the game.
+    Launch,
+    /// Print what the launcher knows about the selected installation.
+    Status,
+}
+
+/// Only the subcommand, used when the rest of the configuration comes from config.json.
+#[derive(Parser)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    /// Print newline-delimited JSON events instead of text.
+    #[clap(long, global = true)]
+    pub json: bool,
+}
+
+/// Runs `command` in the terminal, returns the exit code for the process.
+pub fn run(cfg: &Configuration, command: &Command) -> i32 {
+    let json = cfg.json;
+
+    let (kind, cfg) = match command {
+        Command::Launch => return launch_game(cfg, json),
+        Command::Status => return status(cfg, json),
+        Command::Install => (TaskKind::Install, cfg.clone()),
+        Command::Update => (TaskKind::Update, cfg.clone()),
+        Command::Verify => (TaskKind::Verify, cfg.clone()),
+        Command::Repair => (TaskKind::Repair, cfg.clone()),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let reporter = Reporter::new(kind, tx);
+
+    // ctrl+c cancels the task properly so no half written files are left behind
+    let cancel = reporter.cancel_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    let command = command.clone();
+    let downloads = reporter.clone();
+    task::spawn(reporter, |reporter| async move {
+        match command {
+            Command::Install => installer::install(&cfg, &reporter).await,
+            Command::Verify => verify(&cfg, &reporter).await,
+            Command::Repair => repair::repair(&cfg, &reporter).await,
+            Command::Update => updater::update(&cfg, &reporter).await,
+            Command::Launch | Command::Status => unreachable!("handled without a task"),
+        }
+    });
+
+    print_events(rx, json, &downloads)
+}
+
+async fn verify(cfg: &Configuration, reporter: &Reporter) -> Result<()> {
+    reporter.phase("Fetching reference file list..");
+    let reference = repair::fetch_reference(cfg).await?;
+
+    let damaged = repair::verify(&cfg.path, &reference, reporter)?;
+    for file in &damaged {
+        reporter.warning(format!("{} is missing or damaged", file.path));
+    }
+
+    if !damaged.is_empty() {
+        return Err(LauncherError::Integrity(format!(
+            "{} of {} files are missing or damaged",
+            damaged.len(),
+            reference.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Prints the events of a task until it ends, returns its exit code.
+///
+/// `reporter` is the one the task was started with, it tells how much was downloaded in total.
+fn print_events(rx: mpsc::Receiver<(TaskKind, TaskEvent)>, json: bool, reporter: &Reporter) -> i32 {
+    let mut percent = None;
+    let mut file_percent: HashMap<String, u64> = HashMap::new();
+    let mut summary = None;
+
+    for (kind, event) in rx {
+        match event {
+            TaskEvent::Started if json => emit(json!({ "event": "started", "task": kind })),
+            TaskEvent::Phase(phase) if json => {
+                emit(json!({ "event": "phase", "task": kind, "message": phase }))
+            }
+            TaskEvent::Phase(phase) => println!("{}", phase),
+            TaskEvent::Progress { bytes, total, file } if json => {
+                // one line per percent is plenty, a line per chunk would drown the reader
+                let now = match total {
+                    Some(total) if total > 0 => bytes * 100 / total,
+                    _ => bytes >> 20,
+                };
+                if file_percent.insert(file.clone(), now) != Some(now) {
+                    emit(json!({
+                        "event": "progress",
+                        "task": kind,
+                        "file": file,
+                        "bytes": bytes,
+                        "total": total,
+                    }));
+                }
+            }
+            TaskEvent::Overall { bytes, total } => {
+                if let Some(total) = total.filter(|total| *total > 0 && !json) {
+                    let now = bytes * 100 / total;
+                    if percent != Some(now) {
+                        percent = Some(now);
+                        println!("{}%", now);
+                    }
+                }
+            }
+            TaskEvent::Warning(warning) if json => {
+                emit(json!({ "event": "warning", "task": kind, "message": warning }))
+            }
+            TaskEvent::Warning(warning) => eprintln!("warning: {}", warning),
+            TaskEvent::Outdated(files) if json => {
+                emit(json!({ "event": "outdated", "task": kind, "files": files }))
+            }
+            TaskEvent::Summary(new) => {
+                if !json {
+                    for file in &new.failed {
+                        eprintln!("could not fix {}", file);
+                    }
+                }
+                summary = Some(new);
+            }
+            TaskEvent::Finished => {
+                if json {
+                    emit(json!({
+                        "event": "finished",
+                        "task": kind,
+                        "summary": summary,
+                        "bytes": reporter.downloaded(),
+                    }));
+                }
+                return 0;
+            }
+            TaskEvent::Failed(e) => {
+                if json {
+                    emit(json!({
+                        "event": "failed",
+                        "task": kind,
+                        "error": e.to_string(),
+                        "code": e.exit_code(),
+                        "bytes": reporter.downloaded(),
+                    }));
+                } else {
+                    eprintln!("error: {}", e);
+                }
+                return e.exit_code();
+            }
+            TaskEvent::Cancelled => {
+                if json {
+                    emit(json!({
+                        "event": "cancelled",
+                        "task": kind,
+                        "bytes": reporter.downloaded(),
+                    }));
+                } else {
+                    eprintln!("cancelled");
+                }
+                return LauncherError::Cancelled.exit_code();
+            }
+            _ => (),
+        }
+    }
+
+    // the task went away without telling us how it ended
+    1
+}
+
+/// Writes one line of newline-delimited JSON.
+fn emit(value: Value) {
+    println!("{}", value);
+}
+
+fn launch_game(cfg: &Configuration, json: bool) -> i32 {
+    match launch::launch(cfg) {
+        Ok(child) if json => {
+            emit(json!({ "event": "launched", "pid": child.id() }));
+            0
+        }
+        Ok(child) => {
+            println!("Started the game (pid {})", child.id());
+            0
+        }
+        Err(e) if json => {
+            emit(json!({ "event": "failed", "error": e.to_string(), "code": e.exit_code() }));
+            e.exit_code()
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn status(cfg: &Configuration, json: bool) -> i32 {
+    let verdict = validate::validate(&cfg.path, cfg);
+    let profile = cfg.active_profile().map(|profile| profile.name.clone());
+
+    if json {
+        emit(json!({
+            "event": "status",
+            "installation": cfg.installation,
+            "path": cfg.path,
+            "valid": verdict.is_valid(),
+            "client": verdict.to_string(),
+            "realm": profile,
+            "realmlist": cfg.active_realmlist(),
+            "files": cfg.active_files().len(),
+            "manifest_version": cfg.manifest_version,
+        }));
+    } else {
+        if let Some(installation) = &cfg.installation {
+            println!("Installation: {}", installation);
+        }
+        println!("Path: {}", cfg.path.display());
+        println!("Client: {}", verdict);
+        if let Some(profile) = &profile {
+            println!("Realm: {}", profile);
+        }
+        println!("Realmlist: {}", cfg.active_realmlist());
+        println!("Patch files: {}", cfg.active_files().len());
+        println!("Manifest version: {}", cfg.manifest_version);
+    }
+
+    match verdict {
+        Verdict::Valid { .. } => 0,
+        _ => LauncherError::Config(verdict.to_string()).exit_code(),
+    }
+}
@@ -1,14 +1,85 @@
-use std::{fs::OpenOptions, io::Write};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
 
 use clap::{FromArgMatches, Parser};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    cli::{Cli, Command},
+    error::LauncherError,
+    installation::Installation,
+    launch::Runner,
+    profile::RealmProfile,
+    source::InstallSource,
+};
+
 #[derive(Clone, Debug, Parser, PartialEq, Deserialize, Serialize)]
 pub struct File {
     pub name: String,
     pub path: String,
     pub etag: String,
     pub url: String,
+    /// Expected sha256 of the file, takes precedence over the etag when deciding if it's outdated.
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Binary diffs from older versions to this one, see `delta`.
+    #[clap(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diffs: Vec<Diff>,
+}
+
+/// A bsdiff patch turning the file with hash `from` into the current version.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Diff {
+    pub from: String,
+    pub url: String,
+}
+
+impl File {
+    /// Where the file lives inside the game folder at `root`, `path` may use either separator.
+    ///
+    /// Paths come from the manifest, anything that would end up outside of `root` is refused.
+    pub fn target(&self, root: &Path) -> crate::error::Result<PathBuf> {
+        let separator = |c: char| c == '/' || c == '\\';
+        let outside = || {
+            LauncherError::Config(format!(
+                "{}/{} points outside of the game folder",
+                self.path, self.name
+            ))
+        };
+
+        if self.path.starts_with(separator) {
+            return Err(outside());
+        }
+
+        let mut target = root.to_path_buf();
+        let parts = self.path.split(separator).filter(|part| !part.is_empty());
+        for part in parts.chain([self.name.as_str()]) {
+            if !is_plain_name(part) {
+                return Err(outside());
+            }
+            target.push(part);
+        }
+
+        Ok(target)
+    }
+}
+
+/// Whether `name` is a single file or folder name, no separators, `..` or drive letters.
+pub fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+
+    !name.contains(['/', '\\', ':'])
+        && matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(normal)), None) if normal == name
+        )
 }
 
 impl std::str::FromStr for File {
@@ -31,6 +102,9 @@ impl std::str::FromStr for File {
             path,
             etag,
             url,
+            sha256: None,
+            size: None,
+            diffs: Vec::new(),
         })
     }
 }
@@ -40,24 +114,96 @@ pub struct Configuration {
     #[clap(long, env, required = true, num_args = 1.., value_delimiter = ' ', use_value_delimiter=true)]
     pub files: Vec<File>,
     #[clap(long, env, required = true)]
-    pub path: String,
+    pub path: PathBuf,
     #[clap(long, env, required = true)]
     pub wow: String,
     #[clap(long, env, required = true)]
     pub realmlist: String,
+    /// Named realms to pick from, `realmlist` is used while none is selected.
+    #[clap(skip)]
+    #[serde(default)]
+    pub profiles: Vec<RealmProfile>,
+    #[clap(long, env)]
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// URL of the remote patch manifest, `files` is kept in sync with it on startup.
+    #[clap(long, env)]
+    #[serde(default)]
+    pub manifest: Option<String>,
+    /// Hex encoded ed25519 public key the manifest has to be signed with, required along with `manifest`.
+    #[clap(long, env)]
+    #[serde(default)]
+    pub manifest_key: Option<String>,
+    #[clap(skip)]
+    #[serde(default)]
+    pub manifest_version: u64,
+    /// URL of the reference file list of the base client, used by Repair.
+    #[clap(long, env)]
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// Where Install takes the base client from, downloads `wow` by default.
+    #[clap(skip)]
+    #[serde(default)]
+    pub source: InstallSource,
+    /// How many patch files are downloaded at the same time.
+    #[clap(long, env, default_value_t = 3)]
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Files the manifest or a profile switch retired, the updater removes them from the game folder.
+    #[clap(skip)]
+    #[serde(default)]
+    pub retired: Vec<File>,
+    /// Every game folder the launcher manages, `path` belongs to the selected one.
+    #[clap(skip)]
+    #[serde(default)]
+    pub installations: Vec<Installation>,
+    #[clap(skip)]
+    #[serde(default)]
+    pub installation: Option<String>,
+    /// How the client is started, native on windows and wine everywhere else by default.
+    #[clap(skip)]
+    #[serde(default)]
+    pub runner: Runner,
+    /// Extra environment for the client, e.g. `WINEDEBUG` or `DXVK_HUD`.
+    #[clap(skip)]
+    #[serde(default)]
+    pub launch_env: HashMap<String, String>,
+    /// URL of the addon catalog shown in the Addons tab, see `addons`.
+    #[clap(long, env)]
+    #[serde(default)]
+    pub addons: Option<String>,
+    /// Feed shown in the News panel, see `news`.
+    #[clap(long, env, default_value = DEFAULT_NEWS)]
+    #[serde(default = "default_news")]
+    pub news: String,
+    /// Runs without a window when set, see `cli`.
+    #[clap(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+    /// Print newline-delimited JSON events in headless mode.
+    #[clap(long, global = true)]
+    #[serde(skip)]
+    pub json: bool,
+}
+
+fn default_concurrency() -> usize {
+    3
+}
+
+const DEFAULT_NEWS: &str = "https://raw.githubusercontent.com/Duskhaven/alert2/main/serveralert2";
+
+fn default_news() -> String {
+    DEFAULT_NEWS.to_owned()
 }
 
 impl Configuration {
-    pub fn write(&self) {
-        let mut file = OpenOptions::new()
-            .append(false)
-            .write(true)
-            .truncate(true)
-            .open("config.json")
-            .unwrap();
-        println!("Writing: {:#?}", self);
-        file.write_all(serde_json::to_string_pretty(self).unwrap().as_bytes())
-            .unwrap();
+    /// Saves the configuration to `config.json`, creating it if the launcher was set up through flags only.
+    pub fn write(&self) -> crate::error::Result<()> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| LauncherError::Config(e.to_string()))?;
+        std::fs::write("config.json", json)?;
+
+        Ok(())
     }
 }
 
@@ -67,7 +213,71 @@ pub fn parse() -> Configuration {
 
 pub fn parse_config() -> Configuration {
     match std::fs::File::open("config.json") {
-        Ok(f) => serde_json::from_reader(f).unwrap(),
+        Ok(f) => {
+            let mut cfg: Configuration = serde_json::from_reader(f).unwrap();
+            let cli = Cli::parse();
+            cfg.command = cli.command;
+            cfg.json = cli.json;
+            cfg
+        }
         Err(_) => Configuration::parse(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, name: &str) -> File {
+        File {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            etag: String::new(),
+            url: String::new(),
+            sha256: None,
+            size: None,
+            diffs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn target_accepts_either_separator() {
+        let root = Path::new("game");
+        let expected = root.join("Data").join("enUS").join("patch-enUS-4.MPQ");
+
+        for path in ["Data/enUS", "Data\\enUS", "Data\\enUS/", "Data//enUS"] {
+            assert_eq!(
+                file(path, "patch-enUS-4.MPQ").target(root).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(
+            file("", "Wow.exe").target(root).unwrap(),
+            root.join("Wow.exe")
+        );
+    }
+
+    #[test]
+    fn target_rejects_parent_dirs() {
+        let root = Path::new("game");
+
+        assert!(file("..", "Wow.exe").target(root).is_err());
+        assert!(file("Data/../..", "Wow.exe").target(root).is_err());
+        assert!(file("Data\\..\\..", "Wow.exe").target(root).is_err());
+        assert!(file("Data", "..").target(root).is_err());
+        assert!(file("Data", "../Wow.exe").target(root).is_err());
+        assert!(file("Data", ".").target(root).is_err());
+    }
+
+    #[test]
+    fn target_rejects_absolute_and_drive_paths() {
+        let root = Path::new("game");
+
+        assert!(file("/etc", "passwd").target(root).is_err());
+        assert!(file("\\Windows", "notepad.exe").target(root).is_err());
+        assert!(file("C:\\Windows", "notepad.exe").target(root).is_err());
+        assert!(file("C:", "Wow.exe").target(root).is_err());
+        assert!(file("\\\\server\\share", "Wow.exe").target(root).is_err());
+        assert!(file("Data", "C:Wow.exe").target(root).is_err());
+    }
+}
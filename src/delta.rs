@@ -0,0 +1,84 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use qbsdiff::Bspatch;
+
+use crate::{
+    config::{Diff, File},
+    download,
+    error::{LauncherError, Result},
+    integrity,
+    task::{self, Reporter},
+};
+
+/// Diff the server offers for the installed version of `file`, if any.
+///
+/// Only files with a `sha256` qualify, without it a patched file couldn't be told apart from garbage.
+pub fn find_diff<'a>(file: &'a File, target: &Path) -> Option<&'a Diff> {
+    if file.sha256.is_none() || file.diffs.is_empty() || !target.exists() {
+        return None;
+    }
+
+    let (installed, _) = integrity::hash_file(target).ok()?;
+    file.diffs
+        .iter()
+        .find(|diff| diff.from.eq_ignore_ascii_case(&installed))
+}
+
+/// Downloads `diff` and applies it to the installed `target`, the result ends up in `<target>.part`.
+///
+/// The caller still has to verify the staged file, a diff that produces garbage is no different from a broken download.
+pub async fn apply(
+    file: &File,
+    diff: &Diff,
+    target: &Path,
+    reporter: &Reporter,
+) -> Result<PathBuf> {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".diff");
+    let diff_path = target.with_file_name(name);
+
+    download::download(
+        &diff.url,
+        &diff_path,
+        &reporter.cancel_token(),
+        |progress| reporter.download(format!("{}.diff", file.name), progress),
+    )
+    .await?;
+
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    let staged = target.with_file_name(name);
+
+    let patched = {
+        let (diff_path, target, staged) = (diff_path.clone(), target.to_path_buf(), staged.clone());
+        let name = file.name.clone();
+
+        task::blocking(move || {
+            let patch = fs::read(&diff_path)?;
+            let source = fs::read(target)?;
+
+            let patcher = Bspatch::new(&patch).map_err(|e| {
+                LauncherError::Integrity(format!("broken diff for {}: {}", name, e))
+            })?;
+
+            let mut out = fs::File::create(&staged)?;
+            patcher.apply(&source, &mut out)?;
+            out.sync_all()?;
+
+            Ok(())
+        })
+        .await
+    };
+
+    let _ = fs::remove_file(&diff_path);
+
+    if let Err(e) = patched {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+
+    Ok(staged)
+}
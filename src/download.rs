@@ -0,0 +1,230 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    atomic,
+    error::{LauncherError, Result},
+};
+
+// how often the sidecar gets rewritten while streaming
+const STATE_INTERVAL: u64 = 8 * 1024 * 1024;
+
+lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::new();
+}
+
+/// Client shared by every request the launcher makes so connections get reused.
+pub fn client() -> &'static reqwest::Client {
+    &CLIENT
+}
+
+/// Sidecar stored next to a partial download so it can be resumed on the next attempt.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct State {
+    url: String,
+    /// Validator sent as `If-Range`, either a strong etag or the last-modified date.
+    validator: Option<String>,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+impl State {
+    fn load(path: &Path, url: &str) -> Option<Self> {
+        let state: State = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+        (state.url == url).then_some(state)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec(self).unwrap_or_default())?;
+        Ok(())
+    }
+}
+
+/// Where a download stands, handed to the progress callback after every chunk.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// Everything in the partial file so far, a resumed download starts out with what the last attempt got.
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    /// Only the bytes that came over the network in this attempt.
+    pub fetched: u64,
+}
+
+fn sidecar(dest: &Path, extension: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(extension);
+    dest.with_file_name(name)
+}
+
+/// Downloads `url` into `dest`, picking up where a previous attempt stopped if the server supports ranges.
+///
+/// The data is streamed into `<dest>.part` and only renamed to `dest` once it's complete,
+/// `on_progress` receives the downloaded bytes and the total size if the server sent one, see `Progress`.
+/// Cancelling throws the partial data away instead of keeping it around for a resume.
+pub async fn download(
+    url: &str,
+    dest: &Path,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(Progress),
+) -> Result<()> {
+    let staged = stage(url, dest, cancel, on_progress).await?;
+    atomic::replace(&staged, dest)?;
+
+    Ok(())
+}
+
+/// Same as `download` but stops before the rename, the caller gets the completed `<dest>.part`
+/// so it can verify it before swapping it in.
+pub async fn stage(
+    url: &str,
+    dest: &Path,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<PathBuf> {
+    let part_path = sidecar(dest, ".part");
+    let state_path = sidecar(dest, ".part.json");
+
+    let client = client();
+
+    let mut offset = 0;
+    let mut state = State::load(&state_path, url).unwrap_or_default();
+
+    if let (Some(_), Ok(metadata)) = (&state.validator, fs::metadata(&part_path)) {
+        offset = metadata.len();
+    }
+
+    let mut req = client.get(url);
+    if offset > 0 {
+        eprintln!("Resuming {} at {} bytes", url, offset);
+        req = req
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, state.validator.clone().unwrap());
+    }
+
+    let mut res = req.send().await?;
+
+    if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // the partial file doesn't fit the remote one anymore, start over
+        offset = 0;
+        res = client.get(url).send().await?;
+    }
+
+    let res_status = res.status();
+    let mut res = res.error_for_status()?;
+
+    let resumed = offset > 0
+        && res_status == StatusCode::PARTIAL_CONTENT
+        && res
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .map_or(false, |range| {
+                range.starts_with(&format!("bytes {}-", offset))
+            });
+
+    if !resumed {
+        if res_status == StatusCode::PARTIAL_CONTENT {
+            return Err(LauncherError::Network(format!(
+                "server sent an unexpected range for {}",
+                url
+            )));
+        }
+
+        // either a fresh download or the server ignored / rejected the range
+        offset = 0;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part_path)?;
+
+    state = State {
+        url: url.to_owned(),
+        validator: validator(&res),
+        downloaded: offset,
+        total: res.content_length().map(|len| len + offset),
+    };
+    state.save(&state_path)?;
+
+    let mut since_save = 0;
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = res.chunk() => chunk?,
+            _ = cancel.cancelled() => {
+                drop(file);
+                let _ = fs::remove_file(&part_path);
+                let _ = fs::remove_file(&state_path);
+                return Err(LauncherError::Cancelled);
+            }
+        };
+
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        file.write_all(&chunk)?;
+
+        state.downloaded += chunk.len() as u64;
+        since_save += chunk.len() as u64;
+
+        if since_save >= STATE_INTERVAL {
+            file.flush()?;
+            state.save(&state_path)?;
+            since_save = 0;
+        }
+
+        on_progress(Progress {
+            downloaded: state.downloaded,
+            total: state.total,
+            fetched: state.downloaded - offset,
+        });
+    }
+
+    file.sync_all()?;
+    drop(file);
+
+    if let Some(total) = state.total {
+        if state.downloaded != total {
+            state.save(&state_path)?;
+            return Err(LauncherError::Network(format!(
+                "download of {} ended early ({} of {} bytes)",
+                url, state.downloaded, total
+            )));
+        }
+    }
+
+    let _ = fs::remove_file(&state_path);
+
+    Ok(part_path)
+}
+
+/// Picks something usable as `If-Range`, weak etags aren't allowed there.
+fn validator(res: &reqwest::Response) -> Option<String> {
+    let headers = res.headers();
+
+    headers
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| {
+            headers
+                .get(LAST_MODIFIED)
+                .and_then(|date| date.to_str().ok())
+        })
+        .map(str::to_owned)
+}
@@ -0,0 +1,41 @@
+use std::io;
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, LauncherError>;
+
+/// Everything that can go wrong while installing or updating, shown to the user in the bottom panel.
+#[derive(Debug, Error)]
+pub enum LauncherError {
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("File error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Archive error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl LauncherError {
+    /// Exit code of the headless mode, see `cli`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LauncherError::Config(_) => 2,
+            LauncherError::Network(_) => 3,
+            LauncherError::Integrity(_) => 4,
+            LauncherError::Io(_) | LauncherError::Archive(_) => 5,
+            LauncherError::Cancelled => 130,
+        }
+    }
+}
+
+impl From<reqwest::Error> for LauncherError {
+    fn from(e: reqwest::Error) -> Self {
+        LauncherError::Network(e.to_string())
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Configuration, File},
+    validate::{self, Verdict},
+};
+
+/// A game folder managed by the launcher, e.g. "Live", "PTR" or a clean backup.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Installation {
+    pub name: String,
+    pub path: PathBuf,
+    /// Client locale found when the installation was added, e.g. `enUS`.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Realm profile used by this installation, see `Configuration::profiles`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Files still to be removed from this installation on its next update.
+    #[serde(default)]
+    pub retired: Vec<File>,
+}
+
+impl Installation {
+    pub fn new(name: String, path: PathBuf, cfg: &Configuration) -> Self {
+        let locale = match validate::validate(&path, cfg) {
+            Verdict::Valid { locales, .. } => locales.into_iter().next(),
+            _ => None,
+        };
+
+        Self {
+            name,
+            path,
+            locale,
+            profile: cfg.profile.clone(),
+            retired: Vec::new(),
+        }
+    }
+}
+
+/// `path`, `profile` and `retired` of the configuration always belong to the selected installation,
+/// these keep the list in sync with them.
+impl Configuration {
+    pub fn active_installation(&self) -> Option<&Installation> {
+        let name = self.installation.as_ref()?;
+        self.installations.iter().find(|i| &i.name == name)
+    }
+
+    /// Writes the state of the selected installation back into its entry.
+    fn store_installation(&mut self) {
+        let Some(name) = self.installation.clone() else {
+            return;
+        };
+
+        let (path, profile, retired) = (
+            self.path.clone(),
+            self.profile.clone(),
+            self.retired.clone(),
+        );
+
+        if let Some(installation) = self.installations.iter_mut().find(|i| i.name == name) {
+            installation.path = path;
+            installation.profile = profile;
+            installation.retired = retired;
+        }
+    }
+
+    fn load_installation(&mut self, installation: &Installation) {
+        self.installation = Some(installation.name.clone());
+        self.path = installation.path.clone();
+        self.profile = installation.profile.clone();
+        self.retired = installation.retired.clone();
+    }
+
+    pub fn select_installation(&mut self, name: &str) {
+        self.store_installation();
+
+        if let Some(installation) = self.installations.iter().find(|i| i.name == name).cloned() {
+            self.load_installation(&installation);
+        }
+    }
+
+    /// Adds an installation and selects it, a name that's taken already replaces the old entry.
+    pub fn add_installation(&mut self, installation: Installation) {
+        self.store_installation();
+
+        let name = installation.name.clone();
+        self.installations.retain(|i| i.name != name);
+        self.installations.push(installation);
+
+        self.select_installation(&name);
+    }
+
+    /// Forgets an installation, the files on disk are left alone. The selected one can't be removed.
+    pub fn remove_installation(&mut self, name: &str) {
+        if self.installation.as_deref() == Some(name) {
+            return;
+        }
+
+        self.installations.retain(|i| i.name != name);
+    }
+
+    /// The configuration as seen by every installation, just this one if none were added.
+    pub fn installation_configs(&self) -> Vec<Configuration> {
+        let mut cfg = self.clone();
+        cfg.store_installation();
+
+        if cfg.installations.is_empty() {
+            return vec![cfg];
+        }
+
+        cfg.installations
+            .iter()
+            .map(|installation| {
+                let mut view = cfg.clone();
+                view.load_installation(installation);
+                view
+            })
+            .collect()
+    }
+
+    /// Queues `file` for removal from every installation, not just the selected one.
+    pub fn retire(&mut self, file: File) {
+        let same = |f: &File| f.name == file.name && f.path == file.path;
+
+        for installation in &mut self.installations {
+            if !installation.retired.iter().any(same) {
+                installation.retired.push(file.clone());
+            }
+        }
+
+        if !self.retired.iter().any(same) {
+            self.retired.push(file);
+        }
+    }
+
+    /// Takes `file` off every removal list, it's wanted again.
+    pub fn unretire(&mut self, name: &str, path: &str) {
+        let keep = |f: &File| !(f.name == name && f.path == path);
+
+        for installation in &mut self.installations {
+            installation.retired.retain(keep);
+        }
+        self.retired.retain(keep);
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::File;
+
+/// Streams a file from disk through sha256, returns the hex digest and the amount of bytes read.
+pub fn hash_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1024 * 1024];
+    let mut size = 0;
+
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Whether a digest + size pair is what the config entry expects, entries without a hash always match.
+pub fn expected(file: &File, sha256: &str, size: u64) -> bool {
+    if let Some(expected_size) = file.size {
+        if expected_size != size {
+            return false;
+        }
+    }
+
+    match &file.sha256 {
+        Some(expected) => expected.eq_ignore_ascii_case(sha256),
+        None => true,
+    }
+}
+
+/// Checks the installed copy of `file` at `path`, a missing file is reported as not matching.
+pub fn matches(path: &Path, file: &File) -> io::Result<bool> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    // no need to hash a few hundred megabytes if the size is already off
+    if file.size.map_or(false, |size| size != metadata.len()) {
+        return Ok(false);
+    }
+
+    let (sha256, size) = hash_file(path)?;
+    Ok(expected(file, &sha256, size))
+}
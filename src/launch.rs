@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    process::{Child, Command},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Configuration,
+    error::{LauncherError, Result},
+    validate,
+};
+
+/// How `Wow.exe` gets started.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Runner {
+    /// Run the executable directly, only works on windows.
+    Native,
+    /// Run it through wine, optionally in its own prefix.
+    Wine {
+        #[serde(default)]
+        prefix: Option<PathBuf>,
+    },
+    /// Any other runner, e.g. `proton run` or `lutris`. The executable is appended to `args`.
+    Custom {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        if cfg!(windows) {
+            Runner::Native
+        } else {
+            Runner::Wine { prefix: None }
+        }
+    }
+}
+
+impl Runner {
+    pub fn command(&self, exe: PathBuf, env: &HashMap<String, String>) -> Command {
+        let mut command = match self {
+            Runner::Native => Command::new(&exe),
+            Runner::Wine { prefix } => {
+                let mut command = Command::new("wine");
+                if let Some(prefix) = prefix {
+                    command.env("WINEPREFIX", prefix);
+                }
+                command.arg(&exe);
+                command
+            }
+            Runner::Custom {
+                command: program,
+                args,
+            } => {
+                let mut command = Command::new(program);
+                command.args(args).arg(&exe);
+                command
+            }
+        };
+
+        // the client looks for Data and WTF relative to the working directory
+        if let Some(dir) = exe.parent() {
+            command.current_dir(dir);
+        }
+
+        command.envs(env);
+        command
+    }
+}
+
+/// Starts the client of the selected installation with the configured runner.
+pub fn launch(cfg: &Configuration) -> Result<Child> {
+    let exe = validate::find(&cfg.path, "Wow.exe")
+        .ok_or_else(|| LauncherError::Config(format!("no Wow.exe in {}", cfg.path.display())))?;
+
+    Ok(cfg.runner.command(exe, &cfg.launch_env).spawn()?)
+}
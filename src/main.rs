@@ -1,9 +1,31 @@
 #![feature(async_fn_in_trait)]
 
+pub mod addons;
+pub mod archive;
+pub mod atomic;
+pub mod backup;
+pub mod cli;
 pub mod config;
+pub mod delta;
+pub mod download;
+pub mod error;
+pub mod installation;
 pub mod installer;
+pub mod integrity;
+pub mod launch;
+pub mod manifest;
+pub mod news;
+pub mod platform;
+pub mod profile;
+pub mod realmlist;
+pub mod repair;
+pub mod source;
+pub mod task;
+pub mod toc;
+pub mod transaction;
 pub mod ui;
 pub mod updater;
+pub mod validate;
 
 use egui::{Color32, Style};
 use tokio::runtime::Runtime;
@@ -17,11 +39,26 @@ fn main() {
         ..Default::default()
     };
 
-    let config = config::parse_config();
+    let mut config = config::parse_config();
 
     let rt = Runtime::new().expect("Unable to create Runtime");
     let _enter = rt.enter();
 
+    // finish or undo an update that got interrupted last time
+    if let Err(e) = transaction::recover(&config.path) {
+        eprintln!("Failed to recover interrupted update: {}", e);
+    }
+
+    if let Some(command) = config.command.clone() {
+        // keep going with the local file list if the server is unreachable
+        if let Err(e) = rt.block_on(manifest::sync(&mut config)) {
+            eprintln!("Failed to sync manifest: {}", e);
+        }
+
+        std::process::exit(cli::run(&config, &command));
+    }
+
+    // the window does the manifest sync in the background, see `ui::Ui::new`
     eframe::run_native(
         "Duskhaven Launcher",
         options,
@@ -0,0 +1,235 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Configuration, Diff, File},
+    download,
+    error::{LauncherError, Result},
+};
+
+/// What the server wants the launcher to do with a manifest entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Add,
+    Replace,
+    Retire,
+}
+
+impl Default for Action {
+    fn default() -> Self {
+        Action::Replace
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    #[serde(default)]
+    pub action: Action,
+    pub name: String,
+    pub path: String,
+    // retired entries only need name + path to be identified
+    #[serde(default)]
+    pub etag: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub diffs: Vec<Diff>,
+}
+
+impl Entry {
+    pub fn to_file(&self) -> File {
+        File {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            etag: self.etag.clone(),
+            url: self.url.clone(),
+            sha256: self.sha256.clone(),
+            size: self.size,
+            diffs: self.diffs.clone(),
+        }
+    }
+}
+
+/// Patch manifest published by the server team, see `Configuration::manifest`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub version: u64,
+    pub files: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Downloads the manifest, the detached signature at `<url>.sig` has to match `key`.
+    ///
+    /// The manifest decides which files get written where, so an unsigned one is never accepted.
+    pub async fn fetch(url: &str, key: Option<&str>) -> Result<Self> {
+        let key = key.ok_or_else(|| {
+            LauncherError::Config("manifest_key has to be set to use a manifest".into())
+        })?;
+
+        let body = download::client()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let signature = download::client()
+            .get(format!("{}.sig", url))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        verify(&body, signature.trim(), key)?;
+
+        serde_json::from_slice(&body)
+            .map_err(|e| LauncherError::Config(format!("malformed manifest: {}", e)))
+    }
+}
+
+/// Checks a hex encoded ed25519 signature against a hex encoded public key.
+fn verify(body: &[u8], signature: &str, key: &str) -> Result<()> {
+    let key = hex::decode(key)
+        .ok()
+        .and_then(|key| <[u8; 32]>::try_from(key).ok())
+        .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+        .ok_or_else(|| LauncherError::Config("manifest key is not a valid ed25519 key".into()))?;
+
+    let signature = hex::decode(signature)
+        .ok()
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+        .ok_or_else(|| LauncherError::Integrity("manifest signature is malformed".into()))?;
+
+    key.verify(body, &signature)
+        .map_err(|_| LauncherError::Integrity("manifest signature does not match".into()))
+}
+
+impl Configuration {
+    /// Merges a manifest into the local file list, returns false if it was already applied.
+    pub fn apply_manifest(&mut self, manifest: &Manifest) -> bool {
+        if manifest.version <= self.manifest_version {
+            return false;
+        }
+
+        for entry in &manifest.files {
+            let existing = self
+                .files
+                .iter()
+                .position(|f| f.name == entry.name && f.path == entry.path);
+
+            match (entry.action, existing) {
+                (Action::Retire, Some(i)) => {
+                    let file = self.files.remove(i);
+                    self.retire(file);
+                }
+                (Action::Retire, None) => self.retire(entry.to_file()),
+                (Action::Add | Action::Replace, Some(i)) => self.files[i] = entry.to_file(),
+                (Action::Add | Action::Replace, None) => self.files.push(entry.to_file()),
+            }
+
+            // a file that comes back shouldn't be deleted again
+            if entry.action != Action::Retire {
+                self.unretire(&entry.name, &entry.path);
+            }
+        }
+
+        self.manifest_version = manifest.version;
+        true
+    }
+}
+
+/// Fetches the configured manifest and merges it into `cfg`, writing the config back if anything changed.
+pub async fn sync(cfg: &mut Configuration) -> Result<()> {
+    let Some(url) = cfg.manifest.clone() else {
+        return Ok(());
+    };
+
+    let manifest = Manifest::fetch(&url, cfg.manifest_key.as_deref()).await?;
+
+    if cfg.apply_manifest(&manifest) {
+        eprintln!("Applied manifest version {}", manifest.version);
+        // the manifest is applied for this run either way, it's only fetched again next time
+        if let Err(e) = cfg.write() {
+            eprintln!("warning: could not save config.json: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    const BODY: &[u8] = br#"{"version":1,"files":[]}"#;
+
+    fn signed(body: &[u8]) -> (String, String) {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signature = key.sign(body);
+
+        (
+            hex::encode(signature.to_bytes()),
+            hex::encode(key.verifying_key().to_bytes()),
+        )
+    }
+
+    #[test]
+    fn verify_accepts_matching_signature() {
+        let (signature, key) = signed(BODY);
+        assert!(verify(BODY, &signature, &key).is_ok());
+    }
+
+    #[test]
+    fn verify_rejects_tampered_body() {
+        let (signature, key) = signed(BODY);
+        let tampered = br#"{"version":2,"files":[]}"#;
+
+        assert!(matches!(
+            verify(tampered, &signature, &key),
+            Err(LauncherError::Integrity(_))
+        ));
+    }
+
+    #[test]
+    fn verify_rejects_malformed_signature() {
+        let (_, key) = signed(BODY);
+
+        for signature in ["", "not hex", "abcd"] {
+            assert!(matches!(
+                verify(BODY, signature, &key),
+                Err(LauncherError::Integrity(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn verify_rejects_other_key() {
+        let (signature, _) = signed(BODY);
+        let other = hex::encode(SigningKey::from_bytes(&[8; 32]).verifying_key().to_bytes());
+
+        assert!(matches!(
+            verify(BODY, &signature, &other),
+            Err(LauncherError::Integrity(_))
+        ));
+        assert!(matches!(
+            verify(BODY, &signature, "abcd"),
+            Err(LauncherError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_requires_key() {
+        // fails before any request is made, the url is never resolved
+        let result = Manifest::fetch("http://manifest.invalid/manifest.json", None).await;
+        assert!(matches!(result, Err(LauncherError::Config(_))));
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{download, error::Result};
+
+const CACHE: &str = "news-cache.json";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    /// Shown on top and highlighted, e.g. for an outage or a mandatory update.
+    Urgent,
+}
+
+/// One entry of the news feed, `body` is plain text with `# ` headings and `- ` bullet points.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct NewsItem {
+    pub title: String,
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Turns the feed into items, a feed that isn't JSON is shown as a single plain message.
+fn parse(text: &str) -> Vec<NewsItem> {
+    match serde_json::from_str::<Vec<NewsItem>>(text) {
+        Ok(mut items) => {
+            // urgent first, the feed order is kept otherwise
+            items.sort_by(|a, b| b.severity.cmp(&a.severity));
+            items
+        }
+        Err(_) if text.trim().is_empty() => Vec::new(),
+        Err(_) => vec![NewsItem {
+            title: String::from("Server message"),
+            date: String::new(),
+            body: text.trim().to_owned(),
+            severity: Severity::Info,
+        }],
+    }
+}
+
+/// News from the last successful fetch, so something shows up while offline.
+pub fn cached() -> Vec<NewsItem> {
+    fs::read_to_string(CACHE)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Downloads the feed at `url` and replaces the cache with it.
+pub async fn fetch(url: &str) -> Result<Vec<NewsItem>> {
+    let text = download::client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    let items = parse(&text);
+
+    // the cache is only for the next start, the launcher may well sit in a read-only folder
+    if let Ok(json) = serde_json::to_string_pretty(&items) {
+        if let Err(e) = fs::write(CACHE, json) {
+            eprintln!("Failed to cache news: {}", e);
+        }
+    }
+
+    Ok(items)
+}
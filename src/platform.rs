@@ -0,0 +1,30 @@
+/// Name of the user running the launcher, used for the greeting.
+#[cfg(windows)]
+pub fn username() -> String {
+    use windows::Win32::System::WindowsProgramming::GetUserNameA;
+
+    let mut user: [u8; 256] = [0; 256];
+    let mut user_len = user.len() as u32;
+
+    let ok = unsafe {
+        GetUserNameA(
+            windows::core::PSTR::from_raw(user.as_mut_ptr()),
+            &mut user_len,
+        )
+    };
+
+    if !ok.as_bool() || user_len == 0 {
+        return String::from("there");
+    }
+
+    // the length includes the trailing nul
+    String::from_utf8_lossy(&user[..user_len as usize - 1]).to_string()
+}
+
+/// Name of the user running the launcher, used for the greeting.
+#[cfg(not(windows))]
+pub fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| String::from("there"))
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Configuration, File};
+
+/// A realm the launcher can point the client at, e.g. live, test or PTR.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RealmProfile {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub patchlist: Option<String>,
+    /// Patch files only this realm needs, on top of `Configuration::files`.
+    #[serde(default)]
+    pub files: Vec<File>,
+}
+
+impl RealmProfile {
+    pub fn realmlist(&self) -> String {
+        let mut realmlist = format!("set realmlist {}", self.host);
+
+        if let Some(patchlist) = &self.patchlist {
+            realmlist.push_str(&format!("\r\nset patchlist {}", patchlist));
+        }
+
+        realmlist
+    }
+}
+
+fn same_file(a: &File, b: &File) -> bool {
+    a.name == b.name && a.path == b.path
+}
+
+impl Configuration {
+    pub fn active_profile(&self) -> Option<&RealmProfile> {
+        let name = self.profile.as_ref()?;
+        self.profiles.iter().find(|profile| &profile.name == name)
+    }
+
+    /// Realmlist of the selected profile, falls back to the plain `realmlist` setting.
+    pub fn active_realmlist(&self) -> String {
+        match self.active_profile() {
+            Some(profile) => profile.realmlist(),
+            None => self.realmlist.clone(),
+        }
+    }
+
+    /// Shared patch files plus the ones of the selected profile, the profile wins on conflicts.
+    pub fn active_files(&self) -> Vec<File> {
+        let mut files = self.files.clone();
+
+        if let Some(profile) = self.active_profile() {
+            for file in &profile.files {
+                match files.iter_mut().find(|f| same_file(f, file)) {
+                    Some(existing) => *existing = file.clone(),
+                    None => files.push(file.clone()),
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Selects another profile, files only the old one needed are retired so the next update removes them.
+    pub fn switch_profile(&mut self, name: Option<String>) {
+        let old = self.active_files();
+        self.profile = name;
+        let new = self.active_files();
+
+        for file in old {
+            if !new.iter().any(|f| same_file(f, &file))
+                && !self.retired.iter().any(|f| same_file(f, &file))
+            {
+                self.retired.push(file);
+            }
+        }
+
+        self.retired
+            .retain(|file| !new.iter().any(|f| same_file(f, file)));
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Result, validate};
+
+const REALMLIST: &str = "realmlist.wtf";
+
+fn backup_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Keeps the very first version of `path` around so `restore` can put it back.
+///
+/// A file that didn't exist yet gets a `.orig.none` marker instead, restoring that removes the file again.
+fn backup(path: &Path) -> Result<()> {
+    let backup = backup_path(path, ".orig");
+    let none = backup_path(path, ".orig.none");
+    if backup.exists() || none.exists() {
+        return Ok(());
+    }
+
+    if path.exists() {
+        fs::copy(path, &backup)?;
+    } else {
+        fs::write(&none, "")?;
+    }
+
+    Ok(())
+}
+
+fn restore_file(path: &Path) -> Result<()> {
+    let backup = backup_path(path, ".orig");
+    let none = backup_path(path, ".orig.none");
+
+    if backup.exists() {
+        fs::rename(&backup, path)?;
+    } else if none.exists() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        fs::remove_file(&none)?;
+    }
+
+    Ok(())
+}
+
+/// The host out of a line like `set realmlist logon.example.com`, other lines such as `set patchlist` are skipped.
+pub fn host(realmlist: &str) -> &str {
+    let line = realmlist
+        .lines()
+        .find(|line| {
+            let mut words = line.split_whitespace();
+            let mut next_is =
+                |word: &str| words.next().map_or(false, |w| w.eq_ignore_ascii_case(word));
+            next_is("set") && next_is("realmlist")
+        })
+        .unwrap_or(realmlist);
+
+    line.split_whitespace()
+        .last()
+        .unwrap_or_default()
+        .trim_matches('"')
+}
+
+/// Every realmlist.wtf the client could read, one per locale folder, whether it exists yet or not.
+pub fn realmlist_paths(root: &Path) -> Vec<PathBuf> {
+    let Some(data) = validate::find(root, "Data") else {
+        return Vec::new();
+    };
+
+    validate::locales(&data)
+        .into_iter()
+        .map(|locale| {
+            let dir = data.join(locale);
+            validate::find(&dir, REALMLIST).unwrap_or_else(|| dir.join(REALMLIST))
+        })
+        .collect()
+}
+
+fn config_wtf(root: &Path) -> Option<PathBuf> {
+    validate::find(&validate::find(root, "WTF")?, "Config.wtf")
+}
+
+/// Points every locale of the installation at `realmlist`, backing up what was there before.
+pub fn apply(root: &Path, realmlist: &str) -> Result<Vec<PathBuf>> {
+    let paths = realmlist_paths(root);
+
+    for path in &paths {
+        backup(path)?;
+        fs::write(path, realmlist)?;
+    }
+
+    // the client prefers the realmlist from Config.wtf if it's set there
+    if let Some(config) = config_wtf(root) {
+        backup(&config)?;
+
+        let line = format!("SET realmList \"{}\"", host(realmlist));
+        let mut replaced = false;
+
+        let mut lines: Vec<String> = fs::read_to_string(&config)?
+            .lines()
+            .map(|l| {
+                if l.to_ascii_lowercase().starts_with("set realmlist ") {
+                    replaced = true;
+                    line.clone()
+                } else {
+                    l.to_owned()
+                }
+            })
+            .collect();
+
+        if !replaced {
+            lines.push(line);
+        }
+
+        fs::write(&config, lines.join("\r\n") + "\r\n")?;
+    }
+
+    Ok(paths)
+}
+
+/// Puts back the realmlists the installation had before the launcher touched it.
+pub fn restore(root: &Path) -> Result<()> {
+    for path in realmlist_paths(root) {
+        restore_file(&path)?;
+    }
+
+    if let Some(config) = config_wtf(root) {
+        restore_file(&config)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::RealmProfile;
+
+    /// A fresh game folder with one locale and a Config.wtf under the system temp dir.
+    fn root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "launcher-realmlist-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Data").join("enUS")).unwrap();
+        fs::create_dir_all(root.join("WTF")).unwrap();
+        root
+    }
+
+    fn profile(patchlist: Option<&str>) -> RealmProfile {
+        RealmProfile {
+            name: String::from("PTR"),
+            host: String::from("ptr.example.com"),
+            patchlist: patchlist.map(str::to_owned),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn restore_puts_back_empty_file() {
+        let root = root("empty");
+        let empty = root.join("Data").join("enUS").join(REALMLIST);
+        fs::write(&empty, "").unwrap();
+
+        apply(&root, "set realmlist logon.example.com").unwrap();
+        assert!(fs::read_to_string(&empty)
+            .unwrap()
+            .contains("logon.example.com"));
+        restore(&root).unwrap();
+
+        assert!(empty.exists());
+        assert_eq!(fs::read_to_string(&empty).unwrap(), "");
+        assert!(!backup_path(&empty, ".orig").exists());
+        assert!(!backup_path(&empty, ".orig.none").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn restore_removes_files_that_did_not_exist() {
+        let root = root("missing");
+        let path = root.join("Data").join("enUS").join(REALMLIST);
+
+        apply(&root, "set realmlist logon.example.com").unwrap();
+        assert!(path.exists());
+        restore(&root).unwrap();
+
+        assert!(!path.exists());
+        assert!(!backup_path(&path, ".orig.none").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn host_skips_patchlist() {
+        assert_eq!(host("set realmlist logon.example.com"), "logon.example.com");
+        assert_eq!(
+            host("SET realmList \"logon.example.com\""),
+            "logon.example.com"
+        );
+        assert_eq!(host("logon.example.com"), "logon.example.com");
+
+        let realmlist = profile(Some("http://patch.example.com/")).realmlist();
+        assert_eq!(host(&realmlist), "ptr.example.com");
+
+        let realmlist = "set patchlist http://patch.example.com/\r\nset realmlist ptr.example.com";
+        assert_eq!(host(realmlist), "ptr.example.com");
+    }
+
+    #[test]
+    fn apply_writes_realm_host_to_config_wtf() {
+        let root = root("patchlist");
+        let config = root.join("WTF").join("Config.wtf");
+        fs::write(
+            &config,
+            "SET locale \"enUS\"\r\nSET realmList \"old.example.com\"\r\n",
+        )
+        .unwrap();
+
+        let realmlist = profile(Some("http://patch.example.com/")).realmlist();
+        apply(&root, &realmlist).unwrap();
+
+        let written = fs::read_to_string(&config).unwrap();
+        assert!(written.contains("SET realmList \"ptr.example.com\""));
+        assert!(!written.contains("patch.example.com"));
+        assert!(!written.contains("old.example.com"));
+
+        let locale = fs::read_to_string(root.join("Data").join("enUS").join(REALMLIST)).unwrap();
+        assert_eq!(locale, realmlist);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive, backup,
+    config::{Configuration, File},
+    download,
+    error::{LauncherError, Result},
+    integrity,
+    source::InstallSource,
+    task::{self, Reporter, Summary, TaskEvent},
+    transaction::Transaction,
+    updater,
+};
+
+/// One file of the clean base client, see `Configuration::reference`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReferenceFile {
+    /// Relative to the game folder, always with forward slashes.
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Where the file can be downloaded on its own, otherwise it's taken from the client archive.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl ReferenceFile {
+    fn as_file(&self) -> File {
+        // the separator stays with the folder part so a leading one still counts as absolute
+        let split = self
+            .path
+            .rfind(|c| c == '/' || c == '\\')
+            .map_or(0, |i| i + 1);
+        let (path, name) = self.path.split_at(split);
+
+        File {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            etag: String::new(),
+            url: self.url.clone().unwrap_or_default(),
+            sha256: Some(self.sha256.clone()),
+            size: Some(self.size),
+            diffs: Vec::new(),
+        }
+    }
+}
+
+pub struct Repairer {
+    cfg: Configuration,
+}
+
+impl Repairer {
+    pub fn new(cfg: Configuration) -> Self {
+        Self { cfg }
+    }
+
+    pub fn repair(&self, reporter: Reporter) {
+        let cfg = self.cfg.clone();
+        task::spawn(
+            reporter,
+            |reporter| async move { repair(&cfg, &reporter).await },
+        );
+    }
+}
+
+pub async fn fetch_reference(cfg: &Configuration) -> Result<Vec<ReferenceFile>> {
+    let Some(url) = &cfg.reference else {
+        return Err(LauncherError::Config(
+            "no reference file list configured, repair is not available".into(),
+        ));
+    };
+
+    let body = download::client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    serde_json::from_slice(&body)
+        .map_err(|e| LauncherError::Config(format!("malformed reference file list: {}", e)))
+}
+
+/// Re-hashes every file of the base client and only replaces the broken ones, then runs the updater.
+pub async fn repair(cfg: &Configuration, reporter: &Reporter) -> Result<()> {
+    let root = cfg.path.clone();
+
+    reporter.phase("Fetching reference file list..");
+    let reference = fetch_reference(cfg).await?;
+
+    tokio::task::block_in_place(|| backup::snapshot(&root, reporter))?;
+
+    let damaged = verify(&root, &reference, reporter)?;
+
+    let mut summary = Summary {
+        checked: reference.len(),
+        ..Default::default()
+    };
+
+    if !damaged.is_empty() {
+        let mut transaction = Transaction::begin(&root)?;
+        let staged = restore(
+            cfg,
+            &root,
+            &damaged,
+            &mut transaction,
+            &mut summary,
+            reporter,
+        )
+        .await;
+
+        match staged {
+            Ok(()) => transaction.commit()?,
+            Err(e) => {
+                transaction.rollback()?;
+                return Err(e);
+            }
+        }
+    }
+
+    // patches are checked by hash anyway, this also puts back any broken patch file
+    updater::update(cfg, reporter).await?;
+
+    eprintln!("Repair finished: {}", summary);
+    reporter.phase(summary.to_string());
+    reporter.send(TaskEvent::Summary(summary));
+
+    Ok(())
+}
+
+/// Hashes every reference file on disk, returns the missing or damaged ones.
+pub fn verify(
+    root: &Path,
+    reference: &[ReferenceFile],
+    reporter: &Reporter,
+) -> Result<Vec<ReferenceFile>> {
+    reporter.phase("Verifying game files..");
+
+    let total = reference.iter().map(|file| file.size).sum();
+    let mut checked = 0;
+    let mut damaged = Vec::new();
+
+    for file in reference {
+        reporter.check_cancelled()?;
+
+        let ok = tokio::task::block_in_place(|| {
+            let file = file.as_file();
+            integrity::matches(&file.target(root)?, &file)
+        })?;
+
+        if !ok {
+            eprintln!("{} is missing or damaged", file.path);
+            damaged.push(file.clone());
+        }
+
+        checked += file.size;
+        reporter.progress("verify", checked, Some(total));
+    }
+
+    Ok(damaged)
+}
+
+/// Stages a good copy of every damaged file, files that can't be restored end up in `summary.failed`.
+async fn restore(
+    cfg: &Configuration,
+    root: &Path,
+    damaged: &[ReferenceFile],
+    transaction: &mut Transaction,
+    summary: &mut Summary,
+    reporter: &Reporter,
+) -> Result<()> {
+    for file in damaged {
+        reporter.check_cancelled()?;
+        reporter.phase(format!("Repairing {}", file.path));
+
+        let target = file.as_file().target(root)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match restore_file(cfg, file, &target, reporter).await {
+            Ok(staged) => {
+                transaction.stage(staged, target)?;
+                summary.fixed.push(file.path.clone());
+            }
+            Err(LauncherError::Cancelled) => return Err(LauncherError::Cancelled),
+            Err(e) => {
+                reporter.warning(format!("Could not repair {}: {}", file.path, e));
+                summary.failed.push(file.path.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn restore_file(
+    cfg: &Configuration,
+    file: &ReferenceFile,
+    target: &Path,
+    reporter: &Reporter,
+) -> Result<PathBuf> {
+    let mut staged = target.as_os_str().to_owned();
+    staged.push(".part");
+    let mut staged = PathBuf::from(staged);
+
+    match (&file.url, &cfg.source) {
+        (Some(url), _) => {
+            staged = download::stage(url, target, &reporter.cancel_token(), |progress| {
+                reporter.download(&file.path, progress)
+            })
+            .await?;
+        }
+        (None, InstallSource::Directory(dir)) => {
+            fs::copy(file.as_file().target(dir)?, &staged)?;
+        }
+        (None, InstallSource::Zip(zip)) => {
+            tokio::task::block_in_place(|| archive::extract_file(zip, &file.path, &staged))?;
+        }
+        (None, InstallSource::Http) => {
+            let zip = Path::new("game.zip");
+
+            // the archive is kept after installing, only fetch it again if it's gone
+            if !zip.exists() {
+                reporter.phase("Downloading client archive for repair..");
+                download::download(&cfg.wow, zip, &reporter.cancel_token(), |progress| {
+                    reporter.download("game.zip", progress)
+                })
+                .await?;
+            }
+
+            tokio::task::block_in_place(|| archive::extract_file(zip, &file.path, &staged))?;
+        }
+    }
+
+    if !integrity::matches(&staged, &file.as_file())? {
+        fs::remove_file(&staged)?;
+        return Err(LauncherError::Integrity(format!(
+            "{} does not match the reference",
+            file.path
+        )));
+    }
+
+    Ok(staged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(path: &str) -> ReferenceFile {
+        ReferenceFile {
+            path: path.to_owned(),
+            sha256: String::new(),
+            size: 0,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn reference_paths_stay_in_game_folder() {
+        let root = Path::new("game");
+
+        assert_eq!(
+            reference("Data/common.MPQ").as_file().target(root).unwrap(),
+            root.join("Data").join("common.MPQ")
+        );
+        assert_eq!(
+            reference("Wow.exe").as_file().target(root).unwrap(),
+            root.join("Wow.exe")
+        );
+
+        assert!(reference("../../x").as_file().target(root).is_err());
+        assert!(reference("Data/../../x").as_file().target(root).is_err());
+        assert!(reference("/etc/passwd").as_file().target(root).is_err());
+        assert!(reference("/x").as_file().target(root).is_err());
+        assert!(reference("C:\\Windows\\notepad.exe")
+            .as_file()
+            .target(root)
+            .is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive, download,
+    error::{LauncherError, Result},
+    task::Reporter,
+};
+
+/// Somewhere the base 3.3.5a client can be installed from.
+pub trait Source {
+    /// Puts a complete client into `dest`.
+    async fn install(&self, dest: &Path, reporter: &Reporter) -> Result<()>;
+}
+
+/// Downloads the client archive, see `Configuration::wow`.
+pub struct Http {
+    pub url: String,
+}
+
+impl Source for Http {
+    async fn install(&self, dest: &Path, reporter: &Reporter) -> Result<()> {
+        reporter.phase("Downloading game files...");
+
+        eprintln!("Downloading {:?}", self.url);
+        download::download(
+            &self.url,
+            Path::new("game.zip"),
+            &reporter.cancel_token(),
+            |progress| reporter.download("game.zip", progress),
+        )
+        .await?;
+
+        eprintln!("Done downloading");
+
+        Zip {
+            path: PathBuf::from("game.zip"),
+        }
+        .install(dest, reporter)
+        .await
+    }
+}
+
+/// A client archive that's already on disk.
+pub struct Zip {
+    pub path: PathBuf,
+}
+
+impl Source for Zip {
+    async fn install(&self, dest: &Path, reporter: &Reporter) -> Result<()> {
+        reporter.phase("Installing game files...");
+
+        // extraction is blocking, keep it from starving the other tasks on this worker
+        tokio::task::block_in_place(|| archive::extract(&self.path, dest, reporter))
+    }
+}
+
+/// An existing clean client folder, e.g. on a usb stick.
+pub struct Directory {
+    pub path: PathBuf,
+}
+
+impl Source for Directory {
+    async fn install(&self, dest: &Path, reporter: &Reporter) -> Result<()> {
+        if !self.path.is_dir() {
+            return Err(LauncherError::Config(format!(
+                "{} is not a folder",
+                self.path.display()
+            )));
+        }
+
+        if fs::canonicalize(&self.path)? == fs::canonicalize(dest)? {
+            // nothing to copy, the updater takes it from here
+            return Ok(());
+        }
+
+        reporter.phase("Copying game files...");
+
+        tokio::task::block_in_place(|| {
+            let total = dir_size(&self.path)?;
+            let mut copied = 0;
+
+            copy_dir(&self.path, dest, total, &mut copied, reporter)
+        })
+    }
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+fn copy_dir(
+    from: &Path,
+    to: &Path,
+    total: u64,
+    copied: &mut u64,
+    reporter: &Reporter,
+) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        reporter.check_cancelled()?;
+
+        let entry = entry?;
+        let dest_path = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest_path, total, copied, reporter)?;
+            continue;
+        }
+
+        let mut src = fs::File::open(entry.path())?;
+        let mut dest = fs::File::create(&dest_path)?;
+        let mut buf = vec![0; 256 * 1024];
+
+        loop {
+            let read = src.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+
+            dest.write_all(&buf[..read])?;
+            *copied += read as u64;
+
+            reporter.progress("game files", *copied, Some(total));
+        }
+
+        if let Ok(modified) = entry.metadata()?.modified() {
+            dest.set_modified(modified)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Which source the Settings tab picked, stored in the config.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "path", rename_all = "lowercase")]
+pub enum InstallSource {
+    Http,
+    Zip(PathBuf),
+    Directory(PathBuf),
+}
+
+impl Default for InstallSource {
+    fn default() -> Self {
+        InstallSource::Http
+    }
+}
+
+impl InstallSource {
+    pub async fn install(&self, url: &str, dest: &Path, reporter: &Reporter) -> Result<()> {
+        match self {
+            InstallSource::Http => {
+                Http {
+                    url: url.to_owned(),
+                }
+                .install(dest, reporter)
+                .await
+            }
+            InstallSource::Zip(path) => Zip { path: path.clone() }.install(dest, reporter).await,
+            InstallSource::Directory(path) => {
+                Directory { path: path.clone() }
+                    .install(dest, reporter)
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{mpsc::Sender, Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    download,
+    error::{LauncherError, Result},
+};
+
+/// The long running operations the launcher can kick off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    Install,
+    Update,
+    Patch,
+    Repair,
+    Verify,
+    /// Looks for updates in the background when the launcher starts.
+    Check,
+    /// Installs or updates an addon from the catalog.
+    Addon,
+    /// Takes or restores a snapshot of the player's settings.
+    Backup,
+}
+
+/// Everything a running task tells the outside world about itself.
+#[derive(Debug)]
+pub enum TaskEvent {
+    Started,
+    Phase(String),
+    Progress {
+        bytes: u64,
+        total: Option<u64>,
+        file: String,
+    },
+    /// Sum over every file the task downloaded so far, sent along with each `Progress`.
+    Overall {
+        bytes: u64,
+        total: Option<u64>,
+    },
+    Warning(String),
+    /// Names of the files an update would touch, empty when everything is current.
+    Outdated(Vec<String>),
+    /// What the task ended up doing, sent right before `Finished`.
+    Summary(Summary),
+    Finished,
+    Failed(LauncherError),
+    Cancelled,
+}
+
+/// Handed to a task so it can emit events, tagged with the kind of task they belong to.
+#[derive(Clone)]
+pub struct Reporter {
+    kind: TaskKind,
+    tx: Sender<(TaskKind, TaskEvent)>,
+    ctx: Option<egui::Context>,
+    cancel: CancellationToken,
+    downloads: Arc<Mutex<HashMap<String, (u64, Option<u64>)>>>,
+    /// Bytes that came over the network per file, unlike `downloads` this leaves out extracting and copying.
+    downloaded: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Reporter {
+    pub fn new(kind: TaskKind, tx: Sender<(TaskKind, TaskEvent)>) -> Self {
+        Self {
+            kind,
+            tx,
+            ctx: None,
+            cancel: CancellationToken::new(),
+            downloads: Default::default(),
+            downloaded: Default::default(),
+        }
+    }
+
+    /// Token to stop the task with, the task itself checks it between steps.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Bails out with `LauncherError::Cancelled` once the task got cancelled.
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(LauncherError::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    /// Repaints the window on every event so the UI doesn't lag behind until the mouse moves.
+    pub fn with_repaint(mut self, ctx: egui::Context) -> Self {
+        self.ctx = Some(ctx);
+        self
+    }
+
+    pub fn send(&self, event: TaskEvent) {
+        // nobody listening anymore means the launcher is shutting down
+        let _ = self.tx.send((self.kind, event));
+
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Starts a new step, the overall progress only covers the files of the current one.
+    pub fn phase(&self, phase: impl Into<String>) {
+        self.downloads.lock().unwrap().clear();
+        self.send(TaskEvent::Phase(phase.into()));
+    }
+
+    /// Counts `total` bytes of `file` towards the overall progress before it has started.
+    pub fn expect(&self, file: impl Into<String>, total: u64) {
+        self.downloads
+            .lock()
+            .unwrap()
+            .entry(file.into())
+            .or_insert((0, Some(total)));
+    }
+
+    pub fn progress(&self, file: impl Into<String>, bytes: u64, total: Option<u64>) {
+        let file = file.into();
+
+        let (all_bytes, all_total): (u64, Option<u64>) = {
+            let mut downloads = self.downloads.lock().unwrap();
+            downloads.insert(file.clone(), (bytes, total));
+
+            (
+                downloads.values().map(|(bytes, _)| bytes).sum(),
+                downloads.values().map(|(_, total)| *total).sum(),
+            )
+        };
+
+        self.send(TaskEvent::Progress { bytes, total, file });
+        self.send(TaskEvent::Overall {
+            bytes: all_bytes,
+            total: all_total,
+        });
+    }
+
+    /// Like `progress` for a download, only what came over the network counts towards `downloaded`.
+    pub fn download(&self, file: impl Into<String>, progress: download::Progress) {
+        let file = file.into();
+        self.downloaded
+            .lock()
+            .unwrap()
+            .insert(file.clone(), progress.fetched);
+        self.progress(file, progress.downloaded, progress.total);
+    }
+
+    /// Bytes downloaded by the task so far.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded.lock().unwrap().values().sum()
+    }
+
+    pub fn warning(&self, warning: impl Into<String>) {
+        self.send(TaskEvent::Warning(warning.into()));
+    }
+
+    pub fn finish(&self, result: Result<()>) {
+        match result {
+            Ok(()) => self.send(TaskEvent::Finished),
+            Err(LauncherError::Cancelled) => self.send(TaskEvent::Cancelled),
+            Err(e) => self.send(TaskEvent::Failed(e)),
+        }
+    }
+}
+
+/// Outcome of a task that went over a list of files.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    pub checked: usize,
+    pub fixed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checked {} files, fixed {}",
+            self.checked,
+            self.fixed.len()
+        )?;
+
+        if !self.failed.is_empty() {
+            write!(f, ", {} could not be fixed", self.failed.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs hashing, patching and other long blocking work on the blocking pool.
+///
+/// Unlike `block_in_place` this doesn't hold up the other futures of the same task, e.g. parallel downloads.
+pub async fn blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| LauncherError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?
+}
+
+/// Runs `task` on the tokio runtime, reporting `Started` before and the terminal event after it.
+pub fn spawn<F, Fut>(reporter: Reporter, task: F)
+where
+    F: FnOnce(Reporter) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    reporter.send(TaskEvent::Started);
+    let fut = task(reporter.clone());
+
+    tokio::spawn(async move {
+        let result = fut.await;
+        reporter.finish(result);
+    });
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FileProgress {
+    pub bytes: u64,
+    pub total: Option<u64>,
+}
+
+impl FileProgress {
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| self.bytes as f64 / total as f64)
+    }
+
+    pub fn done(&self) -> bool {
+        self.total.map_or(false, |total| self.bytes >= total)
+    }
+}
+
+/// What the UI knows about a task, built up from its events.
+#[derive(Clone, Debug, Default)]
+pub struct TaskState {
+    pub running: bool,
+    pub phase: String,
+    pub files: BTreeMap<String, FileProgress>,
+    /// Progress over all files of the task.
+    pub overall: FileProgress,
+    pub warnings: Vec<String>,
+    pub summary: Option<Summary>,
+    pub outdated: Option<Vec<String>>,
+    pub error: Option<String>,
+}
+
+impl TaskState {
+    pub fn apply(&mut self, event: TaskEvent) {
+        match event {
+            TaskEvent::Started => {
+                *self = TaskState {
+                    running: true,
+                    ..Default::default()
+                }
+            }
+            TaskEvent::Phase(phase) => self.phase = phase,
+            TaskEvent::Progress { bytes, total, file } => {
+                self.files.insert(file, FileProgress { bytes, total });
+            }
+            TaskEvent::Overall { bytes, total } => self.overall = FileProgress { bytes, total },
+            TaskEvent::Warning(warning) => self.warnings.push(warning),
+            TaskEvent::Outdated(outdated) => self.outdated = Some(outdated),
+            TaskEvent::Summary(summary) => self.summary = Some(summary),
+            TaskEvent::Finished => {
+                self.running = false;
+                self.phase = String::from("Idle");
+            }
+            TaskEvent::Failed(e) => {
+                self.running = false;
+                self.phase = String::from("Failed");
+                self.error = Some(e.to_string());
+            }
+            TaskEvent::Cancelled => {
+                self.running = false;
+                self.phase = String::from("Cancelled");
+            }
+        }
+    }
+
+    /// Overall progress between 0 and 1, none if the size isn't known.
+    pub fn fraction(&self) -> Option<f64> {
+        self.overall.fraction()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::validate;
+
+/// Interface version of the 3.3.5a client, addons made for anything else show up as out of date.
+pub const INTERFACE: &str = "30300";
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_owned())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// The `## Key: Value` header of an addon's .toc file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Toc {
+    /// Keys as written in the file, looked up case-insensitively.
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Toc {
+    pub fn parse(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("##"))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+            .collect();
+
+        Self { fields }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        // some addons ship their .toc in latin-1, don't give up on those
+        let bytes = fs::read(path)?;
+        Ok(Self::parse(&String::from_utf8_lossy(&bytes)))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The title without the `|cffrrggbb ... |r` colour codes addons like to put in there.
+    pub fn title(&self) -> Option<String> {
+        let title = self.get("Title")?;
+        let mut plain = String::new();
+        let mut rest = title;
+
+        while let Some(i) = rest.find('|') {
+            plain.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            let code = &rest[1..];
+
+            if code.starts_with(['c', 'C']) && rest.get(10..).is_some() {
+                rest = &rest[10..];
+            } else if code.starts_with(['r', 'R']) {
+                rest = &rest[2..];
+            } else {
+                plain.push('|');
+                rest = &rest[1..];
+            }
+        }
+        plain.push_str(rest);
+
+        Some(plain.trim().to_owned())
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.get("Version")
+    }
+
+    pub fn interface(&self) -> Option<&str> {
+        self.get("Interface")
+    }
+
+    /// Addons that have to be loaded first, `Dependencies`, `RequiredDeps` and the `Dep` shorthand all count.
+    pub fn dependencies(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|(key, _)| {
+                let key = key.to_ascii_lowercase();
+                key.starts_with("dep") || key == "requireddeps"
+            })
+            .flat_map(|(_, value)| list(value))
+            .collect()
+    }
+
+    /// Global and per character saved variables together.
+    pub fn saved_variables(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|(key, _)| key.to_ascii_lowercase().starts_with("savedvariables"))
+            .flat_map(|(_, value)| list(value))
+            .collect()
+    }
+}
+
+/// The .toc the client loads for the addon folder `dir`, it has to be named after the folder.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let name = dir.file_name()?.to_str()?;
+    validate::find(dir, &format!("{}.toc", name))
+}
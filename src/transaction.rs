@@ -0,0 +1,368 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atomic,
+    error::{LauncherError, Result},
+};
+
+const JOURNAL: &str = "launcher-journal.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+enum State {
+    /// Files are still being downloaded, nothing in the game folder was touched yet.
+    Staging,
+    /// Files are being swapped in, has to be finished or reverted before the game is usable.
+    Committing,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    target: PathBuf,
+    /// Verified new version of `target`, none means the file gets removed.
+    staged: Option<PathBuf>,
+    had_original: bool,
+}
+
+impl Entry {
+    fn backup(&self) -> PathBuf {
+        atomic::backup_path(&self.target)
+    }
+
+    /// Whether the staged file was moved over `target` already, the original is in the backup then.
+    fn swapped_in(&self) -> bool {
+        self.target.exists() && (!self.had_original || self.backup().exists())
+    }
+
+    // every step checks the filesystem first so it can be repeated after a crash
+    fn apply(&self) -> Result<()> {
+        match &self.staged {
+            Some(staged) if staged.exists() => {
+                if self.target.exists() {
+                    fs::rename(&self.target, self.backup())?;
+                }
+                fs::rename(staged, &self.target)?;
+            }
+            Some(_) if self.swapped_in() => (),
+            Some(staged) => {
+                return Err(LauncherError::Integrity(format!(
+                    "{} went missing before it was swapped in",
+                    staged.display()
+                )))
+            }
+            None => {
+                if self.target.exists() {
+                    fs::rename(&self.target, self.backup())?;
+                }
+            }
+        }
+
+        atomic::sync_dir(&self.target)?;
+        Ok(())
+    }
+
+    /// Undoes `apply`, `state` tells whether it could have run at all.
+    fn revert(&self, state: State) -> Result<()> {
+        let backup = self.backup();
+
+        match &self.staged {
+            Some(staged) if staged.exists() => {
+                // interrupted between moving the original away and moving the new one in
+                if state == State::Committing
+                    && self.had_original
+                    && !self.target.exists()
+                    && backup.exists()
+                {
+                    fs::rename(&backup, &self.target)?;
+                }
+                fs::remove_file(staged)?;
+            }
+            // nothing was swapped in while staging, whatever is in the game folder isn't ours
+            _ if state == State::Staging => (),
+            // the new version is in place
+            Some(_) if self.had_original => {
+                if backup.exists() {
+                    fs::rename(&backup, &self.target)?;
+                }
+            }
+            Some(_) => {
+                if self.target.exists() {
+                    fs::remove_file(&self.target)?;
+                }
+            }
+            None => {
+                if self.had_original && !self.target.exists() && backup.exists() {
+                    fs::rename(&backup, &self.target)?;
+                }
+            }
+        }
+
+        atomic::sync_dir(&self.target)?;
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<()> {
+        let backup = self.backup();
+        if self.had_original && backup.exists() {
+            fs::remove_file(backup)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Journal {
+    state: State,
+    entries: Vec<Entry>,
+}
+
+/// A set of file changes inside one installation that is applied as a whole or not at all.
+///
+/// Progress is written to `launcher-journal.json` in the installation so `recover` can
+/// finish or undo a commit that got interrupted by a crash.
+pub struct Transaction {
+    path: PathBuf,
+    journal: Journal,
+}
+
+impl Transaction {
+    pub fn begin(root: &Path) -> Result<Self> {
+        recover(root)?;
+
+        let transaction = Self {
+            path: root.join(JOURNAL),
+            journal: Journal {
+                state: State::Staging,
+                entries: Vec::new(),
+            },
+        };
+        transaction.save()?;
+
+        Ok(transaction)
+    }
+
+    fn save(&self) -> Result<()> {
+        let journal = serde_json::to_vec_pretty(&self.journal)
+            .map_err(|e| LauncherError::Config(e.to_string()))?;
+        fs::write(&self.path, journal)?;
+        fs::File::open(&self.path)?.sync_all()?;
+
+        Ok(())
+    }
+
+    /// Records a verified file that should replace `target` on commit.
+    pub fn stage(&mut self, staged: PathBuf, target: PathBuf) -> Result<()> {
+        self.journal.entries.push(Entry {
+            had_original: target.exists(),
+            target,
+            staged: Some(staged),
+        });
+        self.save()
+    }
+
+    /// Records a file that should be deleted on commit.
+    pub fn remove(&mut self, target: PathBuf) -> Result<()> {
+        if !target.exists() {
+            return Ok(());
+        }
+
+        self.journal.entries.push(Entry {
+            had_original: true,
+            target,
+            staged: None,
+        });
+        self.save()
+    }
+
+    /// Swaps every staged file in, if any of them fails all of them are put back.
+    pub fn commit(mut self) -> Result<()> {
+        self.journal.state = State::Committing;
+        self.save()?;
+
+        if let Err(e) = self.journal.entries.iter().try_for_each(Entry::apply) {
+            self.rollback()?;
+            return Err(e);
+        }
+
+        self.finish()
+    }
+
+    /// Throws away staged files and restores every file the transaction touched.
+    pub fn rollback(self) -> Result<()> {
+        for entry in self.journal.entries.iter().rev() {
+            entry.revert(self.journal.state)?;
+        }
+
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        for entry in &self.journal.entries {
+            entry.finalize()?;
+        }
+
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Cleans up after a transaction that didn't get to finish, should run before touching the installation.
+///
+/// An interrupted commit is completed if all of its staged files are still there, otherwise it's reverted.
+pub fn recover(root: &Path) -> Result<()> {
+    let path = root.join(JOURNAL);
+
+    let journal: Journal = match fs::read(&path) {
+        Ok(journal) => serde_json::from_slice(&journal)
+            .map_err(|e| LauncherError::Config(format!("broken update journal: {}", e)))?,
+        Err(_) => return Ok(()),
+    };
+
+    eprintln!("Recovering interrupted update in {}", root.display());
+
+    let transaction = Transaction { path, journal };
+
+    if transaction.journal.state == State::Committing {
+        let complete = transaction
+            .journal
+            .entries
+            .iter()
+            .all(|entry| match &entry.staged {
+                Some(staged) => staged.exists() || entry.swapped_in(),
+                None => true,
+            });
+
+        if complete
+            && transaction
+                .journal
+                .entries
+                .iter()
+                .try_for_each(Entry::apply)
+                .is_ok()
+        {
+            return transaction.finish();
+        }
+    }
+
+    transaction.rollback()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty folder under the system temp dir.
+    fn root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "launcher-transaction-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// Writes `contents` to `<target>.part` and returns its path, the way downloads are staged.
+    fn staged(target: &Path, contents: &str) -> PathBuf {
+        let mut name = target.as_os_str().to_owned();
+        name.push(".part");
+        let staged = PathBuf::from(name);
+        fs::write(&staged, contents).unwrap();
+        staged
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    /// Marks `transaction` as committing and applies only its first `applied` entries, like a crash would.
+    fn interrupt(mut transaction: Transaction, applied: usize) {
+        transaction.journal.state = State::Committing;
+        transaction.save().unwrap();
+
+        for entry in transaction.journal.entries.iter().take(applied) {
+            entry.apply().unwrap();
+        }
+    }
+
+    #[test]
+    fn recover_completes_interrupted_commit() {
+        let root = root("complete");
+        let (a, b, c) = (root.join("a.MPQ"), root.join("b.MPQ"), root.join("c.MPQ"));
+        fs::write(&a, "old a").unwrap();
+        fs::write(&b, "old b").unwrap();
+        fs::write(&c, "retired").unwrap();
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        transaction.stage(staged(&a, "new a"), a.clone()).unwrap();
+        transaction.stage(staged(&b, "new b"), b.clone()).unwrap();
+        transaction.remove(c.clone()).unwrap();
+        interrupt(transaction, 1);
+
+        recover(&root).unwrap();
+
+        assert_eq!(read(&a), "new a");
+        assert_eq!(read(&b), "new b");
+        assert!(!c.exists());
+        assert!(!atomic::backup_path(&a).exists());
+        assert!(!atomic::backup_path(&b).exists());
+        assert!(!root.join(JOURNAL).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn recover_reverts_commit_with_missing_staged_file() {
+        let root = root("revert");
+        let (a, b, new) = (root.join("a.MPQ"), root.join("b.MPQ"), root.join("new.MPQ"));
+        fs::write(&a, "old a").unwrap();
+        fs::write(&b, "old b").unwrap();
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        transaction.stage(staged(&a, "new a"), a.clone()).unwrap();
+        transaction.stage(staged(&new, "new"), new.clone()).unwrap();
+        let missing = staged(&b, "new b");
+        transaction.stage(missing.clone(), b.clone()).unwrap();
+        interrupt(transaction, 2);
+        fs::remove_file(missing).unwrap();
+
+        recover(&root).unwrap();
+
+        assert_eq!(read(&a), "old a");
+        assert_eq!(read(&b), "old b");
+        assert!(!new.exists());
+        assert!(!atomic::backup_path(&a).exists());
+        assert!(!root.join(JOURNAL).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rollback_while_staging_leaves_game_folder_alone() {
+        let root = root("staging");
+        let a = root.join("a.MPQ");
+        fs::write(&a, "old a").unwrap();
+        // a leftover that isn't ours, it must not be mistaken for a backup of `a`
+        fs::write(atomic::backup_path(&a), "stray").unwrap();
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        let staged_a = staged(&a, "new a");
+        transaction.stage(staged_a.clone(), a.clone()).unwrap();
+        transaction.remove(a.clone()).unwrap();
+        transaction.rollback().unwrap();
+
+        assert_eq!(read(&a), "old a");
+        assert_eq!(read(&atomic::backup_path(&a)), "stray");
+        assert!(!staged_a.exists());
+        assert!(!root.join(JOURNAL).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
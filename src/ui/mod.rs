@@ -1,17 +1,33 @@
 use std::{
-    process::Command,
+    collections::HashMap,
     sync::{
         mpsc::{Receiver, Sender},
         Arc, Mutex,
     },
 };
 
-use egui::{menu, Color32, FontId, ProgressBar, Style, TextStyle};
+use egui::{menu, Button, Color32, FontId, ProgressBar, RichText, Style, TextStyle};
 use lazy_static::lazy_static;
 use native_dialog::{MessageDialog, MessageType};
-use windows::Win32::System::WindowsProgramming::GetUserNameA;
-
-use crate::{config::Configuration, installer::Installer, updater::Updater};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    addons::{self, AddonManager, CatalogEntry, InstalledAddon, Problem},
+    backup::{self, BackupManager, Snapshot},
+    config::Configuration,
+    error::Result,
+    installation::Installation,
+    installer::Installer,
+    launch::{self, Runner},
+    manifest::Manifest,
+    news::{self, NewsItem, Severity},
+    platform, realmlist,
+    repair::Repairer,
+    source::InstallSource,
+    task::{self, Reporter, TaskEvent, TaskKind, TaskState},
+    updater::{self, Updater},
+    validate::{self, Verdict},
+};
 
 lazy_static! {
     static ref TAB: Arc<Mutex<i32>> = Arc::new(Mutex::new(0));
@@ -22,24 +38,24 @@ lazy_static! {
 pub struct Ui {
     pub name: String,
     pub cfg: Configuration,
-    pub update_text: String,
-    pub installing_text: String,
     pub status_text: String,
-    pub install_status: bool,
-    pub progress: f64,
-    pub server_message: String,
-
-    tx: Sender<u32>,
-    rx: Receiver<u32>,
-
-    tx_install: Sender<u32>,
-    rx_install: Receiver<u32>,
-
-    tx_status: Sender<String>,
-    rx_status: Receiver<String>,
-
-    tx_progress_install: Sender<f64>,
-    rx_progress_install: Receiver<f64>,
+    pub news: Vec<NewsItem>,
+    pub news_loading: bool,
+    pub catalog: Vec<CatalogEntry>,
+    pub catalog_loading: bool,
+    pub addons: Vec<InstalledAddon>,
+    pub snapshots: Vec<Snapshot>,
+    /// Set once a request to the server failed, cached data is shown meanwhile.
+    pub offline: bool,
+    pub error: Option<String>,
+    pub tasks: HashMap<TaskKind, TaskState>,
+    pub verdict: Verdict,
+
+    new_installation: String,
+    rx_startup: Receiver<Startup>,
+    cancel_tokens: HashMap<TaskKind, CancellationToken>,
+    tx_events: Sender<(TaskKind, TaskEvent)>,
+    rx_events: Receiver<(TaskKind, TaskEvent)>,
 }
 
 impl Ui {
@@ -67,49 +83,419 @@ impl Ui {
 
         // initalize
 
-        unsafe {
-            let mut user: [u8; 256] = [0; 256];
-            let mut user_len = user.len() as u32;
-
-            GetUserNameA(
-                windows::core::PSTR::from_raw(user.as_mut_ptr()),
-                &mut user_len,
-            );
-
-            let (tx, rx) = std::sync::mpsc::channel();
-            let (tx_install, rx_install) = std::sync::mpsc::channel();
-            let (tx_status, rx_status) = std::sync::mpsc::channel();
-            let (tx_progress_install, rx_progress_install) = std::sync::mpsc::channel();
-
-            tx_status.send("Idle".to_owned()).unwrap();
-
-
-            let news = reqwest::blocking::get("https://raw.githubusercontent.com/Duskhaven/alert2/main/serveralert2").unwrap().text().unwrap();
-
-            return Self {
-                name: String::from_utf8_lossy(&user[..user_len as usize]).to_string(),
-                cfg,
-                update_text: String::from("Check for Updates"),
-                installing_text: String::from("Install"),
-                status_text: String::from("Idle"),
-                tx,
-                rx,
-                progress: 0.0,
-                tx_install,
-                rx_install,
-                tx_status,
-                rx_status,
-                tx_progress_install,
-                rx_progress_install,
-                server_message: news,
-                install_status: false,
-            };
+        let (tx_events, rx_events) = std::sync::mpsc::channel();
+
+        // nothing in here may wait on the network, the window has to show up right away
+        let (tx_startup, rx_startup) = std::sync::mpsc::channel();
+        start_background(&cfg, tx_startup, &cc.egui_ctx);
+
+        let catalog_loading = cfg.addons.is_some();
+        let addons = addons::installed(&cfg.path);
+        let snapshots = backup::snapshots(&cfg.path);
+
+        Self {
+            name: platform::username(),
+            verdict: validate::validate(&cfg.path, &cfg),
+            cfg,
+            status_text: String::from("Idle"),
+            news: news::cached(),
+            news_loading: true,
+            catalog: Vec::new(),
+            catalog_loading,
+            addons,
+            snapshots,
+            offline: false,
+            error: None,
+            tasks: HashMap::new(),
+            new_installation: String::new(),
+            rx_startup,
+            cancel_tokens: HashMap::new(),
+            tx_events,
+            rx_events,
+        }
+    }
+
+    fn reporter(&mut self, kind: TaskKind, ctx: &egui::Context) -> Reporter {
+        let reporter = Reporter::new(kind, self.tx_events.clone()).with_repaint(ctx.clone());
+        self.cancel_tokens.insert(kind, reporter.cancel_token());
+        reporter
+    }
+
+    fn task(&self, kind: TaskKind) -> TaskState {
+        self.tasks.get(&kind).cloned().unwrap_or_default()
+    }
+
+    /// Writes the configuration, a failure only costs the change on the next start so it's shown, not fatal.
+    fn save(&mut self) {
+        if let Err(e) = self.cfg.write() {
+            self.error = Some(format!("Could not save config.json: {}", e));
+        }
+    }
+
+    fn select_installation(&mut self, name: &str) {
+        self.cfg.select_installation(name);
+        self.save();
+        self.verdict = validate::validate(&self.cfg.path, &self.cfg);
+        self.addons = addons::installed(&self.cfg.path);
+        self.snapshots = backup::snapshots(&self.cfg.path);
+    }
+
+    /// Whether a task is running that the buttons have to wait for, the update check doesn't count.
+    fn busy(&self) -> bool {
+        self.tasks
+            .iter()
+            .any(|(kind, task)| *kind != TaskKind::Check && task.running)
+    }
+
+    /// Picks up whatever the startup work finished since the last frame.
+    fn poll_startup(&mut self, ctx: &egui::Context) {
+        while let Ok(result) = self.rx_startup.try_recv() {
+            match result {
+                Startup::News(Ok(news)) => {
+                    self.news_loading = false;
+                    self.news = news;
+                }
+                Startup::News(Err(e)) => {
+                    println!("Failed to fetch news: {}", e);
+                    self.news_loading = false;
+                    self.offline = true;
+                }
+                Startup::Catalog(catalog) => {
+                    self.catalog_loading = false;
+                    match catalog {
+                        Ok(catalog) => self.catalog = catalog,
+                        Err(e) => {
+                            println!("Failed to fetch addon catalog: {}", e);
+                            self.offline = true;
+                        }
+                    }
+                }
+                Startup::Manifest(manifest) => {
+                    match manifest {
+                        Ok(Some(manifest)) => {
+                            if self.cfg.apply_manifest(&manifest) {
+                                println!("Applied manifest version {}", manifest.version);
+                                self.save();
+                            }
+                        }
+                        Ok(None) => (),
+                        // keep going with the local file list if the server is unreachable
+                        Err(e) => {
+                            println!("Failed to sync manifest: {}", e);
+                            self.offline = true;
+                        }
+                    }
+
+                    // the file list is final now, see if anything needs updating
+                    if !self.busy() {
+                        let reporter = self.reporter(TaskKind::Check, ctx);
+                        let cfg = self.cfg.clone();
+                        task::spawn(reporter, |reporter| async move {
+                            updater::check_outdated(&cfg, &reporter).await
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Folds everything the tasks sent since the last frame into their state.
+    fn poll_events(&mut self) {
+        while let Ok((kind, event)) = self.rx_events.try_recv() {
+            // the startup check is only a hint, it shouldn't look like an error or block anything
+            if kind == TaskKind::Check {
+                match &event {
+                    TaskEvent::Failed(e) => {
+                        println!("Failed to check for updates: {}", e);
+                        self.offline = true;
+                    }
+                    TaskEvent::Finished => self.status_text = String::from("Idle"),
+                    TaskEvent::Phase(phase) => self.status_text = phase.clone(),
+                    _ => (),
+                }
+
+                self.tasks.entry(kind).or_default().apply(event);
+                continue;
+            }
+
+            match &event {
+                TaskEvent::Started => self.error = None,
+                TaskEvent::Phase(phase) => self.status_text = phase.clone(),
+                TaskEvent::Finished => {
+                    self.status_text = String::from("Idle");
+
+                    // install and repair take a snapshot on their own
+                    self.snapshots = backup::snapshots(&self.cfg.path);
+
+                    if kind == TaskKind::Addon {
+                        self.addons = addons::installed(&self.cfg.path);
+                    }
+
+                    // whatever the check found is stale after an update
+                    if let Some(check) = self.tasks.get_mut(&TaskKind::Check) {
+                        check.outdated = None;
+                    }
+                }
+                TaskEvent::Failed(e) => {
+                    self.status_text = String::from("Idle");
+                    self.error = Some(e.to_string());
+                }
+                TaskEvent::Cancelled => self.status_text = String::from("Cancelled"),
+                _ => (),
+            }
+
+            self.tasks.entry(kind).or_default().apply(event);
         }
     }
 }
 
+/// Tooltip with the .toc metadata of an installed addon.
+fn addon_details(addon: &InstalledAddon) -> String {
+    let mut details = vec![format!("Folder: {}", addon.folder)];
+
+    if let Some(interface) = addon.toc.interface() {
+        details.push(format!("Interface: {}", interface));
+    }
+
+    let dependencies = addon.toc.dependencies();
+    if !dependencies.is_empty() {
+        details.push(format!("Dependencies: {}", dependencies.join(", ")));
+    }
+
+    let saved = addon.toc.saved_variables();
+    if !saved.is_empty() {
+        details.push(format!("SavedVariables: {}", saved.join(", ")));
+    }
+
+    details.join("\n")
+}
+
+/// What the user clicked in the Addons tab, applied once the lists aren't borrowed anymore.
+enum AddonAction {
+    Install(CatalogEntry),
+    Uninstall(Vec<String>),
+    SetEnabled(String, bool),
+    Fix(InstalledAddon),
+}
+
+/// Results of the network work started along with the window.
+enum Startup {
+    News(Result<Vec<NewsItem>>),
+    Catalog(Result<Vec<CatalogEntry>>),
+    Manifest(Result<Option<Manifest>>),
+}
+
+/// Fetches news and the manifest on the runtime, the results come back through `tx`.
+fn start_background(cfg: &Configuration, tx: Sender<Startup>, ctx: &egui::Context) {
+    let url = cfg.news.clone();
+    let news_tx = tx.clone();
+    let news_ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        let _ = news_tx.send(Startup::News(news::fetch(&url).await));
+        news_ctx.request_repaint();
+    });
+
+    if let Some(url) = cfg.addons.clone() {
+        let catalog_tx = tx.clone();
+        let catalog_ctx = ctx.clone();
+
+        tokio::spawn(async move {
+            let _ = catalog_tx.send(Startup::Catalog(addons::fetch_catalog(&url).await));
+            catalog_ctx.request_repaint();
+        });
+    }
+
+    let manifest = cfg.manifest.clone();
+    let key = cfg.manifest_key.clone();
+    let ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        let result = match manifest {
+            Some(url) => Manifest::fetch(&url, key.as_deref()).await.map(Some),
+            None => Ok(None),
+        };
+
+        let _ = tx.send(Startup::Manifest(result));
+        ctx.request_repaint();
+    });
+}
+
+/// Renders one news entry, urgent ones get a red frame so they can't be missed.
+fn news_item(ui: &mut egui::Ui, item: &NewsItem) {
+    let color = match item.severity {
+        Severity::Info => Color32::WHITE,
+        Severity::Warning => Color32::YELLOW,
+        Severity::Urgent => Color32::RED,
+    };
+
+    let mut frame = egui::Frame::group(ui.style());
+    if item.severity == Severity::Urgent {
+        frame = frame.stroke(egui::Stroke::new(2.0, Color32::RED));
+    }
+
+    frame.show(ui, |ui| {
+        ui.set_width(ui.available_width());
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(&item.title).strong().color(color));
+            if !item.date.is_empty() {
+                ui.label(RichText::new(&item.date).weak());
+            }
+        });
+
+        for line in item.body.lines() {
+            let line = line.trim_end();
+
+            if let Some(heading) = line.strip_prefix("# ") {
+                ui.label(RichText::new(heading).strong());
+            } else if let Some(bullet) = line.strip_prefix("- ") {
+                ui.label(format!("  • {}", bullet));
+            } else if line.is_empty() {
+                ui.add_space(5.0);
+            } else {
+                ui.label(line);
+            }
+        }
+    });
+
+    ui.add_space(5.0);
+}
+
+impl Ui {
+    fn addons_tab(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.heading("Addons");
+        ui.separator();
+
+        let busy = self.busy();
+        let mut action = None;
+
+        egui::ScrollArea::vertical()
+            .id_source("addons")
+            .show(ui, |ui| {
+                ui.label("Installed");
+                ui.add_space(10.0);
+
+                if self.addons.is_empty() {
+                    ui.label(RichText::new("No addons installed").weak());
+                }
+
+                for addon in &self.addons {
+                    let entry = self
+                        .catalog
+                        .iter()
+                        .find(|entry| entry.name.eq_ignore_ascii_case(&addon.folder));
+
+                    ui.horizontal(|ui| {
+                        let title = RichText::new(addon.title());
+                        ui.label(if addon.enabled { title } else { title.weak() })
+                            .on_hover_text(addon_details(addon));
+
+                        if let Some(version) = addon.toc.version() {
+                            ui.label(RichText::new(version).weak());
+                        }
+
+                        ui.add_enabled_ui(!busy, |ui| {
+                            if let Some(entry) =
+                                entry.filter(|entry| entry.update_available(&self.addons))
+                            {
+                                if ui.button(format!("Update to {}", entry.version)).clicked() {
+                                    action = Some(AddonAction::Install(entry.clone()));
+                                }
+                            }
+
+                            let toggle = if addon.enabled { "Disable" } else { "Enable" };
+                            if ui.button(toggle).clicked() {
+                                action = Some(AddonAction::SetEnabled(
+                                    addon.folder.clone(),
+                                    !addon.enabled,
+                                ));
+                            }
+
+                            if ui.button("Uninstall").clicked() {
+                                let folders = entry
+                                    .map(|entry| entry.folders())
+                                    .unwrap_or_else(|| vec![addon.folder.clone()]);
+                                action = Some(AddonAction::Uninstall(folders));
+                            }
+
+                            if addon.problems.iter().any(Problem::fixable)
+                                && ui.button("Fix folder").clicked()
+                            {
+                                action = Some(AddonAction::Fix(addon.clone()));
+                            }
+                        });
+                    });
+
+                    for problem in &addon.problems {
+                        ui.colored_label(Color32::YELLOW, format!("  {}", problem));
+                    }
+                }
+
+                ui.add_space(20.0);
+                ui.separator();
+                ui.label("Catalog");
+                ui.add_space(10.0);
+
+                if self.catalog_loading {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Loading catalog..");
+                    });
+                } else if self.catalog.is_empty() {
+                    ui.label(RichText::new("No addons available").weak());
+                }
+
+                for entry in &self.catalog {
+                    let installed = self
+                        .addons
+                        .iter()
+                        .any(|addon| addon.folder.eq_ignore_ascii_case(&entry.name));
+
+                    ui.horizontal(|ui| {
+                        ui.label(&entry.name);
+                        ui.label(RichText::new(&entry.version).weak());
+
+                        if installed {
+                            ui.label(RichText::new("Installed").color(Color32::GREEN));
+                        } else if ui.add_enabled(!busy, Button::new("Install")).clicked() {
+                            action = Some(AddonAction::Install(entry.clone()));
+                        }
+                    });
+
+                    if !entry.description.is_empty() {
+                        ui.label(&entry.description);
+                    }
+                    ui.add_space(5.0);
+                }
+            });
+
+        let result = match action {
+            Some(AddonAction::Install(entry)) => {
+                let reporter = self.reporter(TaskKind::Addon, ctx);
+                AddonManager::new(self.cfg.clone()).install(entry, reporter);
+                Ok(())
+            }
+            Some(AddonAction::Uninstall(folders)) => addons::uninstall(&self.cfg.path, &folders),
+            Some(AddonAction::SetEnabled(folder, enabled)) => {
+                addons::set_enabled(&self.cfg.path, &folder, enabled)
+            }
+            Some(AddonAction::Fix(addon)) => addons::fix_folders(&self.cfg.path, &addon),
+            None => return,
+        };
+
+        if let Err(e) = result {
+            self.error = Some(e.to_string());
+        }
+        self.addons = addons::installed(&self.cfg.path);
+    }
+}
+
 impl eframe::App for Ui {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_startup(ctx);
+        self.poll_events();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             menu::bar(ui, |ui| {
                 if ui.button("About").clicked() {
@@ -128,7 +514,8 @@ impl eframe::App for Ui {
                 }
                 ui.add_space(50.0);
                 if ui.button("Addons").clicked() {
-                    *TAB.lock().unwrap() = 0;
+                    *TAB.lock().unwrap() = 2;
+                    self.addons = addons::installed(&self.cfg.path);
                 }
             });
 
@@ -150,25 +537,303 @@ impl eframe::App for Ui {
                     ui.label("remember to set your wow folder in the settings if you want to patch your current installation");
                     ui.add_space(20.0);
 
-                    
-                }
-                1 => {
-                    ui.heading("Settings");
-                    ui.separator();
-                    ui.label("Set your game path here");
-                    if ui.button("Select Folder").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                            self.cfg.path = Some(path.into_os_string().into_string().unwrap()).unwrap();
-                            self.cfg.write();
+                    if self.news.is_empty() && self.news_loading {
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Loading news..");
+                        });
+                        ui.add_space(20.0);
+                    }
+
+                    if !self.news.is_empty() {
+                        ui.separator();
+                        ui.label("News");
+                        if self.offline {
+                            ui.label(RichText::new("Offline, showing the last news we got").weak());
+                        }
+                        ui.add_space(10.0);
+
+                        egui::ScrollArea::vertical()
+                            .id_source("news")
+                            .max_height(250.0)
+                            .show(ui, |ui| {
+                                for item in &self.news {
+                                    news_item(ui, item);
+                                }
+                            });
+                        ui.add_space(20.0);
+                    }
+
+                    let repair = self.task(TaskKind::Repair);
+                    if let Some(summary) = &repair.summary {
+                        ui.separator();
+                        ui.label("Last repair");
+                        ui.add_space(10.0);
+                        ui.label(summary.to_string());
+
+                        for file in &summary.fixed {
+                            ui.colored_label(Color32::GREEN, format!("Fixed {}", file));
+                        }
+                        for file in &summary.failed {
+                            ui.colored_label(Color32::RED, format!("Could not fix {}", file));
+                        }
+                        ui.add_space(20.0);
+                    }
+
+                    let downloads: Vec<_> = self
+                        .tasks
+                        .values()
+                        .filter(|task| task.running)
+                        .flat_map(|task| task.files.iter())
+                        .filter(|(_, progress)| !progress.done())
+                        .collect();
+
+                    if !downloads.is_empty() {
+                        ui.separator();
+                        ui.label("Downloads");
+                        ui.add_space(10.0);
+
+                        for (file, progress) in downloads {
+                            ui.horizontal(|ui| {
+                                ui.label(file);
+                                ui.add(
+                                    ProgressBar::new(progress.fraction().unwrap_or(0.0) as f32)
+                                        .show_percentage(),
+                                );
+                            });
                         }
                     }
-                    // add space
-                    ui.add_space(5.0);
-                    ui.label(format!("Current directory: {}", self.cfg.path));
                 }
-                _ => {
-                    todo!()
+                1 => {
+                    egui::ScrollArea::vertical().id_source("settings").show(ui, |ui| {
+                        ui.heading("Settings");
+                        ui.separator();
+
+                        ui.label("Installations");
+                        ui.add_space(5.0);
+
+                        let busy = self.busy();
+                        let mut select = None;
+                        let mut remove = None;
+
+                        for installation in &self.cfg.installations {
+                            let active = self.cfg.installation.as_ref() == Some(&installation.name);
+
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(!busy, egui::SelectableLabel::new(active, &installation.name))
+                                    .clicked()
+                                {
+                                    select = Some(installation.name.clone());
+                                }
+
+                                ui.label(installation.path.display().to_string());
+                                if let Some(locale) = &installation.locale {
+                                    ui.label(locale);
+                                }
+
+                                if !active && ui.add_enabled(!busy, Button::new("Remove")).clicked() {
+                                    remove = Some(installation.name.clone());
+                                }
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.new_installation);
+
+                            let name = self.new_installation.trim().to_owned();
+                            if ui
+                                .add_enabled(!busy && !name.is_empty(), Button::new("Add installation"))
+                                .clicked()
+                            {
+                                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                    let installation = Installation::new(name, path, &self.cfg);
+                                    self.cfg.add_installation(installation);
+                                    self.new_installation.clear();
+                                    select = self.cfg.installation.clone();
+                                }
+                            }
+                        });
+
+                        if self.cfg.installations.len() > 1 {
+                            let patch_text = if self.task(TaskKind::Patch).running {
+                                "Patching.."
+                            } else {
+                                "Patch all installations"
+                            };
+
+                            if ui.add_enabled(!busy, Button::new(patch_text)).clicked() {
+                                let reporter = self.reporter(TaskKind::Patch, ctx);
+                                Updater::new(self.cfg.clone()).install_patches(reporter);
+                            }
+                        }
+
+                        if let Some(name) = remove {
+                            self.cfg.remove_installation(&name);
+                            self.save();
+                        }
+
+                        if let Some(name) = select {
+                            self.select_installation(&name);
+                        }
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.label("Set your game path here");
+                        if ui.button("Select Folder").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                self.cfg.path = path;
+                                self.save();
+                                self.verdict = validate::validate(&self.cfg.path, &self.cfg);
+                            }
+                        }
+                        // add space
+                        ui.add_space(5.0);
+                        ui.label(format!("Current directory: {}", self.cfg.path.display()));
+
+                        let color = if self.verdict.is_valid() {
+                            Color32::GREEN
+                        } else {
+                            Color32::YELLOW
+                        };
+                        ui.colored_label(color, self.verdict.to_string());
+
+                        ui.add_space(5.0);
+                        if ui.button("Restore original realmlist").clicked() {
+                            if let Err(e) = realmlist::restore(&self.cfg.path) {
+                                self.error = Some(e.to_string());
+                            }
+                        }
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.label("Install the game from");
+
+                        let mut source = self.cfg.source.clone();
+
+                        ui.radio_value(&mut source, InstallSource::Http, "Download");
+
+                        ui.horizontal(|ui| {
+                            let selected = matches!(source, InstallSource::Zip(_));
+                            if ui.radio(selected, "Local archive").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Client archive", &["zip"])
+                                    .pick_file()
+                                {
+                                    source = InstallSource::Zip(path);
+                                }
+                            }
+                            if let InstallSource::Zip(path) = &source {
+                                ui.label(path.display().to_string());
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            let selected = matches!(source, InstallSource::Directory(_));
+                            if ui.radio(selected, "Existing folder").clicked() {
+                                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                    source = InstallSource::Directory(path);
+                                }
+                            }
+                            if let InstallSource::Directory(path) = &source {
+                                ui.label(path.display().to_string());
+                            }
+                        });
+
+                        if source != self.cfg.source {
+                            self.cfg.source = source;
+                            self.save();
+                        }
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.label("Start the game with");
+
+                        let mut runner = self.cfg.runner.clone();
+
+                        ui.radio_value(&mut runner, Runner::Native, "Wow.exe");
+
+                        ui.horizontal(|ui| {
+                            let selected = matches!(runner, Runner::Wine { .. });
+                            if ui.radio(selected, "Wine").clicked() && !selected {
+                                runner = Runner::Wine { prefix: None };
+                            }
+                            if let Runner::Wine { prefix } = &mut runner {
+                                if ui.button("Select WINEPREFIX").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                        *prefix = Some(path);
+                                    }
+                                }
+                                if let Some(prefix) = prefix {
+                                    ui.label(prefix.display().to_string());
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            let selected = matches!(runner, Runner::Custom { .. });
+                            if ui.radio(selected, "Custom command").clicked() && !selected {
+                                runner = Runner::Custom {
+                                    command: String::new(),
+                                    args: Vec::new(),
+                                };
+                            }
+                            if let Runner::Custom { command, .. } = &mut runner {
+                                ui.text_edit_singleline(command);
+                            }
+                        });
+
+                        if runner != self.cfg.runner {
+                            self.cfg.runner = runner;
+                            self.save();
+                        }
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.label("Backups of your settings and addons");
+
+                        let busy = self.busy();
+
+                        if ui.add_enabled(!busy, Button::new("Back up now")).clicked() {
+                            let reporter = self.reporter(TaskKind::Backup, ctx);
+                            BackupManager::new(self.cfg.clone()).snapshot(reporter);
+                        }
+
+                        let mut restore = None;
+                        let mut delete = None;
+
+                        for snapshot in &self.snapshots {
+                            ui.horizontal(|ui| {
+                                ui.label(snapshot.name());
+                                ui.label(
+                                    RichText::new(format!("{:.1} MB", snapshot.size as f64 / 1e6)).weak(),
+                                );
+
+                                if ui.add_enabled(!busy, Button::new("Restore")).clicked() {
+                                    restore = Some(snapshot.path.clone());
+                                }
+                                if ui.add_enabled(!busy, Button::new("Delete")).clicked() {
+                                    delete = Some(snapshot.clone());
+                                }
+                            });
+                        }
+
+                        if let Some(path) = restore {
+                            let reporter = self.reporter(TaskKind::Backup, ctx);
+                            BackupManager::new(self.cfg.clone()).restore(path, reporter);
+                        }
+
+                        if let Some(snapshot) = delete {
+                            if let Err(e) = backup::delete(&snapshot) {
+                                self.error = Some(e.to_string());
+                            }
+                            self.snapshots = backup::snapshots(&self.cfg.path);
+                        }
+                    });
                 }
+                2 => self.addons_tab(ui, ctx),
+                _ => (),
             }
         });
 
@@ -179,7 +844,7 @@ impl eframe::App for Ui {
 
             ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
                 if ui.button("Launch").clicked() {
-                    match Command::new(self.cfg.path.clone() + "\\Wow.exe").spawn() {
+                    match launch::launch(&self.cfg) {
                         Ok(_) => (),
                         Err(e) => {
                             MessageDialog::new()
@@ -192,61 +857,153 @@ impl eframe::App for Ui {
                     }
                 }
 
-                if let Ok(status) = self.rx.try_recv() {
-                    if status == 1 {
-                        self.update_text = String::from("Updating..");
-                    } else {
-                        self.update_text = String::from("Check for Updates");
+                let busy = self.busy();
+
+                if !self.cfg.installations.is_empty() {
+                    let mut selected = self.cfg.installation.clone();
+
+                    ui.add_enabled_ui(!busy, |ui| {
+                        egui::ComboBox::from_id_source("installation")
+                            .selected_text(selected.clone().unwrap_or_default())
+                            .show_ui(ui, |ui| {
+                                for installation in &self.cfg.installations {
+                                    ui.selectable_value(
+                                        &mut selected,
+                                        Some(installation.name.clone()),
+                                        &installation.name,
+                                    );
+                                }
+                            });
+                    });
+
+                    if selected != self.cfg.installation {
+                        if let Some(name) = selected {
+                            self.select_installation(&name);
+                        }
+                    }
+                }
+
+                if !self.cfg.profiles.is_empty() {
+                    let mut selected = self.cfg.profile.clone();
+                    let current = self
+                        .cfg
+                        .active_profile()
+                        .map(|profile| profile.name.clone())
+                        .unwrap_or_else(|| "Default".to_owned());
+
+                    ui.add_enabled_ui(!busy, |ui| {
+                        egui::ComboBox::from_id_source("realm_profile")
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut selected, None, "Default");
+                                for profile in &self.cfg.profiles {
+                                    ui.selectable_value(
+                                        &mut selected,
+                                        Some(profile.name.clone()),
+                                        &profile.name,
+                                    );
+                                }
+                            });
+                    });
+
+                    // switching runs the updater right away so realmlist and patches match the realm
+                    if selected != self.cfg.profile {
+                        self.cfg.switch_profile(selected);
+                        self.save();
+
+                        let reporter = self.reporter(TaskKind::Update, ctx);
+                        Updater::new(self.cfg.clone()).check(reporter);
                     }
                 }
 
-                if ui.button(&self.update_text).clicked() {
+                let busy = self.busy();
+
+                let update_text = if self.task(TaskKind::Update).running {
+                    "Updating.."
+                } else {
+                    "Check for Updates"
+                };
+
+                if ui.add_enabled(!busy, Button::new(update_text)).clicked() {
                     // Its kinda shit to create a new instance everytime but its not resource intensive anyways
                     // but i kinda have to since i cant implement a lifetime without the egui trait crying around.
-                    Updater::new(self.cfg.clone()).check(
-                        self.tx_status.clone(),
-                        self.tx.clone(),
-                        ctx.clone(),
-                    );
+                    let reporter = self.reporter(TaskKind::Update, ctx);
+                    Updater::new(self.cfg.clone()).check(reporter);
                 }
 
-                if let Ok(install_status) = self.rx_install.try_recv() {
-                    self.install_status = install_status != 0;
-                    if install_status == 1 {
-                        self.installing_text = String::from("Installing..");
-                    } else {
-                        self.installing_text = String::from("Install");
-                    }
+                let installing_text = if self.task(TaskKind::Install).running {
+                    "Installing.."
+                } else {
+                    "Install"
+                };
+
+                if ui
+                    .add_enabled(!busy, Button::new(installing_text))
+                    .clicked()
+                {
+                    let reporter = self.reporter(TaskKind::Install, ctx);
+                    Installer::new(self.cfg.clone()).clean_install(reporter);
                 }
 
-                if ui.button("Install").clicked() {
-                    Installer::new(self.cfg.clone()).clean_install(
-                        self.tx_status.clone(),
-                        self.tx_install.clone(),
-                        self.tx.clone(),
-                        self.tx_progress_install.clone(),
-                        ctx.clone(),
-                    ); 
+                let repair_text = if self.task(TaskKind::Repair).running {
+                    "Repairing.."
+                } else {
+                    "Repair"
+                };
+
+                if ui.add_enabled(!busy, Button::new(repair_text)).clicked() {
+                    let reporter = self.reporter(TaskKind::Repair, ctx);
+                    Repairer::new(self.cfg.clone()).repair(reporter);
                 }
 
-                if let Ok(status) = self.rx_status.try_recv() {
-                    self.status_text = status;
+                if busy && ui.button("Cancel").clicked() {
+                    for (kind, token) in &self.cancel_tokens {
+                        if self.task(*kind).running {
+                            token.cancel();
+                        }
+                    }
                 }
 
                 // add x spacing
                 ui.add_space(200.0);
                 ui.label(format!("Status: {}", self.status_text));
-                ui.add_space(50.0);
 
-                if let Ok(progress) = self.rx_progress_install.try_recv() {
-                    self.progress = progress;
+                if self.offline {
+                    ui.add_space(20.0);
+                    ui.colored_label(Color32::YELLOW, "Offline");
+                }
+
+                match &self.task(TaskKind::Check).outdated {
+                    Some(outdated) if !outdated.is_empty() => {
+                        ui.add_space(20.0);
+                        ui.colored_label(
+                            Color32::YELLOW,
+                            format!("{} updates available", outdated.len()),
+                        );
+                    }
+                    Some(_) => {
+                        ui.add_space(20.0);
+                        ui.colored_label(Color32::GREEN, "Up to date");
+                    }
+                    None => (),
                 }
 
-                let bar = ProgressBar::new(self.progress as f32).animate(true);
+                if let Some(error) = &self.error {
+                    ui.add_space(20.0);
+                    ui.colored_label(Color32::RED, error);
+                }
+
+                ui.add_space(50.0);
+
+                let progress = self
+                    .tasks
+                    .values()
+                    .filter(|task| task.running)
+                    .find_map(|task| task.fraction());
 
-                if self.progress != 0.0 {
-                    ui.label(format!("{:.2}%", self.progress * 100.0));
-                    ui.add(bar);
+                if let Some(progress) = progress {
+                    ui.label(format!("{:.2}%", progress * 100.0));
+                    ui.add(ProgressBar::new(progress as f32).animate(true));
                 }
             });
 
@@ -1,11 +1,19 @@
 use std::{
-    fs::{self, DirEntry, OpenOptions},
-    io::Write,
-    path::PathBuf,
-    sync::mpsc::Sender,
+    fs,
+    path::{Path, PathBuf},
 };
 
-use crate::config::Configuration;
+use futures::{stream, StreamExt};
+
+use crate::{
+    config::{Configuration, File},
+    delta, download,
+    error::{LauncherError, Result},
+    integrity, realmlist,
+    task::{self, Reporter, Summary, TaskEvent},
+    transaction::Transaction,
+    validate::{self, Verdict},
+};
 
 pub struct Updater {
     cfg: Configuration,
@@ -16,163 +24,293 @@ impl Updater {
         Self { cfg }
     }
 
-    pub fn check(&self, status_tx: Sender<String>, tx: Sender<u32>, ctx: egui::Context) {
-        let files = self.cfg.files.clone();
-        let path = self.cfg.path.clone();
-        let list = self.cfg.realmlist.clone();
-        tokio::spawn(async move {
-            tx.send(1).unwrap();
-            ctx.request_repaint();
-            status_tx.send("Updating..".to_owned()).unwrap();
-
-            // find the data folder
-            let dir: Vec<_> = fs::read_dir(&path)
-                .unwrap()
-                .filter(|entry| {
-                    if let Ok(entry) = entry {
-                        return entry.file_name() == "Data";
-                    } else {
-                        false
-                    }
-                })
-                .collect();
-
-            // find the lang specific folder
-            let lang_path: Vec<_> = fs::read_dir(dir[0].as_ref().clone().unwrap().path())
-                .unwrap()
-                .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_dir())
-                .collect();
-
-            let mut file = OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .append(false)
-                .open(format!(
-                    "{}\\realmlist.wtf",
-                    lang_path[0].as_ref().unwrap().path().display()
-                ))
-                .unwrap();
-
-            file.write_all(list.as_bytes()).unwrap();
-
-            /*   for entry in dir {
-                let entry = entry.unwrap();
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .open(format!("{}\\Data\\realmlist.wtf", entry.path().display()))
-                    .unwrap();
-
-                file.write_all(list.as_bytes()).unwrap();
-            } */
-
-            for file in &files {
-                let res = reqwest::get(&file.url).await.unwrap();
-
-                if res
-                    .headers()
-                    .get("etag")
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .replace(r#"""#, "")
-                    != file.etag
-                {
-                    //Resource is outdated
-                    status_tx
-                        .send(format!("Updating file: {}", file.path))
-                        .unwrap();
-
-                    let absolute_path = format!("{}\\{}", path, file.path);
-
-                    std::fs::remove_file(&absolute_path).unwrap();
-
-                    // Write new resource file
-                    let mut file = std::fs::File::create(&absolute_path).unwrap();
-                    file.write_all(&res.bytes().await.unwrap()).unwrap();
-                }
+    pub fn check(&self, reporter: Reporter) {
+        let cfg = self.cfg.clone();
+        task::spawn(
+            reporter,
+            |reporter| async move { update(&cfg, &reporter).await },
+        );
+    }
+
+    pub fn install_patches(&self, reporter: Reporter) {
+        let cfg = self.cfg.clone();
+        task::spawn(
+            reporter,
+            |reporter| async move { patch(&cfg, &reporter).await },
+        );
+    }
+}
+
+/// Brings the files of the configured installation up to date.
+pub async fn update(cfg: &Configuration, reporter: &Reporter) -> Result<()> {
+    reporter.phase("Updating..");
+
+    let path = cfg.path.clone();
+
+    check_installation(&path, cfg)?;
+    realmlist::apply(&path, &cfg.active_realmlist())?;
+
+    let mut summary = Summary::default();
+    let mut transaction = Transaction::begin(&path)?;
+    let staged = stage_files(cfg, &path, &mut transaction, &mut summary, reporter).await;
+    finish(transaction, staged)?;
+
+    reporter.send(TaskEvent::Summary(summary));
+    Ok(())
+}
+
+/// Downloads every outdated file of the installation at `path` into the transaction.
+async fn stage_files(
+    cfg: &Configuration,
+    path: &Path,
+    transaction: &mut Transaction,
+    summary: &mut Summary,
+    reporter: &Reporter,
+) -> Result<()> {
+    let files = cfg.active_files();
+    let outdated = outdated(&files, path, reporter).await?;
+
+    if !outdated.is_empty() {
+        reporter.phase(format!("Updating {} files..", outdated.len()));
+    }
+
+    fetch_all(cfg, path, &outdated, transaction, reporter).await?;
+
+    summary.checked = files.len();
+    summary.fixed = outdated.iter().map(|file| file.name.clone()).collect();
+
+    for file in &cfg.retired {
+        transaction.remove(file.target(path)?)?;
+    }
+
+    Ok(())
+}
+
+/// Looks for updates without downloading anything, reported as `TaskEvent::Outdated`.
+pub async fn check_outdated(cfg: &Configuration, reporter: &Reporter) -> Result<()> {
+    reporter.phase("Checking for updates..");
+
+    // nothing to compare against, the user has to pick a folder or install first
+    if !cfg.path.exists() {
+        return Ok(());
+    }
+
+    let files = cfg.active_files();
+    let mut names: Vec<String> = outdated(&files, &cfg.path, reporter)
+        .await?
+        .iter()
+        .map(|file| file.name.clone())
+        .collect();
+
+    for file in &cfg.retired {
+        if file.target(&cfg.path)?.exists() {
+            names.push(file.name.clone());
+        }
+    }
+
+    reporter.send(TaskEvent::Outdated(names));
+    Ok(())
+}
+
+/// The files of `files` that are missing or differ from what the server has.
+async fn outdated<'a>(
+    files: &'a [File],
+    path: &Path,
+    reporter: &Reporter,
+) -> Result<Vec<&'a File>> {
+    let mut outdated = Vec::new();
+
+    for file in files {
+        reporter.check_cancelled()?;
+        let absolute_path = file.target(path)?;
+
+        // with a hash we know for sure, no request needed if the local copy is intact
+        if file.sha256.is_some()
+            && tokio::task::block_in_place(|| integrity::matches(&absolute_path, file))?
+        {
+            continue;
+        }
+
+        if file.sha256.is_none() && absolute_path.exists() {
+            let res = download::client().head(&file.url).send().await?;
+            let etag = res
+                .headers()
+                .get("etag")
+                .and_then(|etag| etag.to_str().ok())
+                .map(|etag| etag.replace(r#"""#, ""));
+
+            // some CDNs strip the etag, nothing to compare against in that case
+            if etag.map_or(true, |etag| etag == file.etag) {
+                continue;
             }
+        }
 
-            status_tx.send("Idle".to_owned()).unwrap();
-        });
-    }
-
-    pub fn install_patches(
-        &self,
-        progress_tx: Sender<f64>,
-        status_tx: Sender<String>,
-        tx: Sender<u32>,
-        ctx: egui::Context,
-    ) {
-        let files = self.cfg.files.clone();
-        let path = self.cfg.path.clone();
-        let list = self.cfg.realmlist.clone();
-
-        tokio::spawn(async move {
-            tx.send(1).unwrap();
-            ctx.request_repaint();
-            status_tx.send("Patching..".to_owned()).unwrap();
-
-            // overwrite the realmlist since i cba to check if it's outdated
-            let dir = fs::read_dir(path.clone())
-                .unwrap()
-                .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_dir());
-
-            for entry in dir {
-                let mut e = entry.unwrap().path();
-
-                let mut data_path = e.clone();
-                data_path.push("Data");
-
-                // find any folder in this directory which will be as example: enUS/enGB/deDE
-                let lang_dir = fs::read_dir(data_path.clone())
-                    .unwrap()
-                    .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_dir())
-                    .next()
-                    .unwrap()
-                    .unwrap();
-
-                let mut realm_path: PathBuf = PathBuf::new();
-
-                // find realmlist.wtf since manually resolving it does not work for some reason and i cba to figure out why
-                for entry in fs::read_dir(lang_dir.path()).unwrap() {
-                    let e = entry.unwrap();
-
-                    if e.path().to_str().unwrap().contains("realmlist") {
-                        realm_path = e.path();
-                    }
-                }
+        //Resource is outdated
+        outdated.push(file);
+    }
 
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .open(realm_path.clone())
-                    .unwrap();
+    Ok(outdated)
+}
+
+/// Commits the transaction if staging went through, otherwise puts everything back.
+fn finish(transaction: Transaction, staged: Result<()>) -> Result<()> {
+    match staged {
+        Ok(()) => transaction.commit(),
+        Err(e) => {
+            transaction.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+/// Downloads every patch into each installation the launcher manages.
+pub async fn patch(cfg: &Configuration, reporter: &Reporter) -> Result<()> {
+    reporter.phase("Patching..");
+
+    for cfg in cfg.installation_configs() {
+        let e = cfg.path.clone();
+
+        let verdict = validate::validate(&e, &cfg);
+        if verdict == Verdict::NotWow {
+            reporter.warning(format!("Skipping {}: {}", e.display(), verdict));
+            continue;
+        }
+
+        check_installation(&e, &cfg)?;
 
-                file.write_all(list.as_bytes()).unwrap();
+        // overwrite the realmlist since i cba to check if it's outdated
+        realmlist::apply(&e, &cfg.active_realmlist())?;
 
-                for file in &files {
-                    status_tx
-                        .send(format!("Patching file: {}", file.name))
-                        .unwrap();
-                    let mut res = reqwest::get(&file.url).await.unwrap();
+        let mut transaction = Transaction::begin(&e)?;
+        let staged = patch_files(&cfg, &e, &mut transaction, reporter).await;
+        finish(transaction, staged)?;
+    }
+
+    Ok(())
+}
+
+/// Downloads every patch into the transaction, regardless of what's installed already.
+async fn patch_files(
+    cfg: &Configuration,
+    path: &Path,
+    transaction: &mut Transaction,
+    reporter: &Reporter,
+) -> Result<()> {
+    let files = cfg.active_files();
+    reporter.phase(format!("Patching {} files..", files.len()));
+
+    let files: Vec<_> = files.iter().collect();
+    fetch_all(cfg, path, &files, transaction, reporter).await?;
+
+    for file in &cfg.retired {
+        transaction.remove(file.target(path)?)?;
+    }
 
-                    let mut downloaded = 0;
-                    let total_size = res.content_length().unwrap_or(0);
+    Ok(())
+}
 
-                    let absolute_path = &e.join(file.path.clone()).join(file.name.clone());
-                    let mut patch_file = std::fs::File::create(&absolute_path).unwrap();
+/// Downloads `files` with up to `Configuration::concurrency` running at once and stages each as soon as it's verified.
+async fn fetch_all(
+    cfg: &Configuration,
+    path: &Path,
+    files: &[&File],
+    transaction: &mut Transaction,
+    reporter: &Reporter,
+) -> Result<()> {
+    // otherwise the overall total only grows as downloads get going and the bar jumps back
+    for file in files {
+        if let Some(size) = file.size {
+            reporter.expect(&file.name, size);
+        }
+    }
 
-                    while let Some(chunk) = res.chunk().await.unwrap() {
-                        downloaded += chunk.len();
-                        patch_file.write(&chunk).unwrap();
+    let mut downloads = stream::iter(files)
+        .map(|file| async move {
+            let target = file.target(path)?;
+            let staged = fetch(file, &target, reporter).await?;
 
-                        let progress = downloaded as f64 / total_size as f64;
+            Ok::<_, LauncherError>((staged, target))
+        })
+        .buffer_unordered(cfg.concurrency.max(1));
 
-                        progress_tx.send(progress).unwrap();
-                    }
+    // bailing out drops the other downloads, their partial files are picked up again next time
+    while let Some(download) = downloads.next().await {
+        let (staged, target) = download?;
+        transaction.stage(staged, target)?;
+    }
+
+    Ok(())
+}
+
+/// Downloads `file` next to `target` and verifies it, returns the staged copy.
+async fn fetch(file: &File, target: &Path, reporter: &Reporter) -> Result<PathBuf> {
+    // a diff from the installed version is a lot smaller than the whole file
+    let diff = {
+        let (file, target) = (file.clone(), target.to_path_buf());
+        task::blocking(move || Ok(delta::find_diff(&file, &target).cloned())).await?
+    };
+
+    if let Some(diff) = diff {
+        let patched = match delta::apply(file, &diff, target, reporter).await {
+            Ok(staged) => verify_blocking(staged, file).await,
+            Err(e) => Err(e),
+        };
+
+        match patched {
+            Ok(staged) => {
+                // the whole file was expected up front, it's done without being downloaded
+                if let Some(size) = file.size {
+                    reporter.progress(&file.name, size, Some(size));
                 }
+                return Ok(staged);
             }
+            Err(LauncherError::Cancelled) => return Err(LauncherError::Cancelled),
+            Err(e) => reporter.warning(format!(
+                "Diff for {} failed ({}), downloading the whole file",
+                file.name, e
+            )),
+        }
+    }
 
-            status_tx.send("Idle".to_owned()).unwrap();
-        });
+    let staged = download::stage(&file.url, target, &reporter.cancel_token(), |progress| {
+        reporter.download(&file.name, progress)
+    })
+    .await?;
+
+    verify_blocking(staged, file).await
+}
+
+/// `verify` off the runtime, so other downloads keep streaming while a large file is hashed.
+async fn verify_blocking(staged: PathBuf, file: &File) -> Result<PathBuf> {
+    let file = file.clone();
+    task::blocking(move || verify(&staged, &file).map(|_| staged)).await
+}
+
+/// Refuses to patch anything that isn't a complete 3.3.5a client.
+fn check_installation(path: &Path, cfg: &Configuration) -> Result<()> {
+    match validate::validate(path, cfg) {
+        Verdict::Valid { .. } => Ok(()),
+        verdict => Err(LauncherError::Config(format!(
+            "{}: {}",
+            path.display(),
+            verdict
+        ))),
+    }
+}
+
+/// Hashes a freshly downloaded file and deletes it again if it doesn't match the config entry.
+fn verify(path: &Path, file: &File) -> Result<()> {
+    if file.sha256.is_none() && file.size.is_none() {
+        return Ok(());
     }
+
+    if integrity::matches(path, file)? {
+        return Ok(());
+    }
+
+    fs::remove_file(path)?;
+    Err(LauncherError::Integrity(format!(
+        "{} does not match the expected hash",
+        file.name
+    )))
 }
@@ -0,0 +1,194 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::Configuration, integrity};
+
+/// Build number of the 3.3.5a client.
+pub const BUILD: u32 = 12340;
+
+const LOCALES: &[&str] = &[
+    "enUS", "enGB", "enCN", "enTW", "deDE", "esES", "esMX", "frFR", "itIT", "koKR", "ptBR", "ptPT",
+    "ruRU", "zhCN", "zhTW",
+];
+
+const CORE_MPQS: &[&str] = &[
+    "common.MPQ",
+    "common-2.MPQ",
+    "expansion.MPQ",
+    "lichking.MPQ",
+    "patch.MPQ",
+    "patch-2.MPQ",
+    "patch-3.MPQ",
+];
+
+const LOCALE_MPQS: &[&str] = &[
+    "locale-{}.MPQ",
+    "expansion-locale-{}.MPQ",
+    "lichking-locale-{}.MPQ",
+    "patch-{}.MPQ",
+    "patch-{}-2.MPQ",
+    "patch-{}-3.MPQ",
+];
+
+/// What the validator thinks of a folder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Valid { build: u32, locales: Vec<String> },
+    WrongVersion { version: String },
+    Incomplete { missing: Vec<String> },
+    NotWow,
+}
+
+impl Verdict {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Verdict::Valid { .. })
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Valid { build, locales } => {
+                write!(f, "Valid client (build {}, {})", build, locales.join(", "))
+            }
+            Verdict::WrongVersion { version } => {
+                write!(
+                    f,
+                    "Wrong client version {}, 3.3.5a ({}) is required",
+                    version, BUILD
+                )
+            }
+            Verdict::Incomplete { missing } => {
+                write!(f, "Incomplete installation, missing {}", missing.join(", "))
+            }
+            Verdict::NotWow => write!(f, "Not a World of Warcraft folder"),
+        }
+    }
+}
+
+/// Checks that `path` holds a complete 3.3.5a client before anything gets patched.
+pub fn validate(path: &Path, cfg: &Configuration) -> Verdict {
+    let exe = find(path, "Wow.exe");
+    let data = find(path, "Data");
+
+    let (Some(exe), Some(data)) = (exe, data) else {
+        return Verdict::NotWow;
+    };
+
+    // a custom executable we shipped ourselves doesn't need to look like the original
+    if !known_executable(&exe, cfg) {
+        match detect_version(&exe) {
+            Some((_, _, _, build)) if build == BUILD => (),
+            Some((major, minor, patch, build)) => {
+                return Verdict::WrongVersion {
+                    version: format!("{}.{}.{}.{}", major, minor, patch, build),
+                };
+            }
+            None => {
+                return Verdict::WrongVersion {
+                    version: String::from("unknown"),
+                };
+            }
+        }
+    }
+
+    let locales = locales(&data);
+    let mut missing: Vec<String> = CORE_MPQS
+        .iter()
+        .filter(|mpq| find(&data, mpq).is_none())
+        .map(|mpq| format!("Data/{}", mpq))
+        .collect();
+
+    if locales.is_empty() {
+        missing.push(String::from("Data/<locale>"));
+    }
+
+    for locale in &locales {
+        let dir = data.join(locale);
+
+        for mpq in LOCALE_MPQS {
+            let mpq = mpq.replace("{}", locale);
+            if find(&dir, &mpq).is_none() {
+                missing.push(format!("Data/{}/{}", locale, mpq));
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        return Verdict::Incomplete { missing };
+    }
+
+    Verdict::Valid {
+        build: BUILD,
+        locales,
+    }
+}
+
+/// Locale folders (enUS, deDE, ..) inside a Data folder.
+pub fn locales(data: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(data) else {
+        return Vec::new();
+    };
+
+    let mut locales: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map_or(false, |t| t.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            LOCALES
+                .iter()
+                .any(|locale| locale.eq_ignore_ascii_case(name))
+        })
+        .collect();
+
+    locales.sort();
+    locales
+}
+
+/// Case insensitive lookup since the client doesn't care but linux does.
+pub fn find(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .eq_ignore_ascii_case(name)
+        })
+        .map(|entry| entry.path())
+}
+
+/// Reads the file version out of the executable's VS_FIXEDFILEINFO.
+fn detect_version(exe: &Path) -> Option<(u32, u32, u32, u32)> {
+    const SIGNATURE: [u8; 4] = 0xFEEF04BDu32.to_le_bytes();
+
+    let bytes = fs::read(exe).ok()?;
+    let start = bytes.windows(4).position(|window| window == SIGNATURE)?;
+
+    let read = |offset: usize| -> Option<u32> {
+        let bytes = bytes.get(start + offset..start + offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    };
+
+    // signature, struct version, then the file version split into two dwords
+    let ms = read(8)?;
+    let ls = read(12)?;
+
+    Some((ms >> 16, ms & 0xffff, ls >> 16, ls & 0xffff))
+}
+
+/// Whether the executable is the one our own patch list ships.
+fn known_executable(exe: &Path, cfg: &Configuration) -> bool {
+    cfg.files
+        .iter()
+        .filter(|file| file.name.eq_ignore_ascii_case("wow.exe") && file.sha256.is_some())
+        .any(|file| integrity::matches(exe, file).unwrap_or(false))
+}